@@ -1,7 +1,7 @@
 use std::{io::{self, BufRead}, time::Instant};
 
 use fes::{
-    bit_board::BitBoardGame, game::{ChessGame, Move}, perft_bb_mover::PerftMove
+    bit_board::BitBoardGame, game::{ChessGame, Move}, perft_bb_mover::PerftDivide, perft_bench
 };
 
 pub fn perft<Game: ChessGame>(gs: &mut Game, limit: usize) -> usize {
@@ -51,15 +51,9 @@ fn main() {
                             gs = BitBoardGame::from_fen(&fen).unwrap();
                         }
                         "move" => {
-                            'uci: for ucimov in parts {
-                                for mov in gs.moves().iter() {
-                                    if ucimov == mov.to_uci() {
-                                        gs.do_move(mov);
-                                        continue 'uci;
-                                    }
-                                }
-                                println!("warn: unknown uci move {ucimov}! stopping");
-                                break;
+                            let moves: Vec<&str> = parts.collect();
+                            if let Err(i) = gs.play_uci(&moves) {
+                                println!("warn: unknown uci move {}! stopping", moves[i]);
                             }
                         }
                         "perft" => {
@@ -68,12 +62,17 @@ fn main() {
                             perft_div(&mut gs, depth);
                             println!("{}ms", now.elapsed().as_millis());
                         }
+                        "bench" => {
+                            let depth = parts.next().unwrap().parse::<usize>().unwrap();
+                            let (nodes, elapsed, nps) = perft_bench(&mut gs, depth);
+                            println!("{nodes} nodes {}ms {nps:.0} nps", elapsed.as_millis());
+                        }
                         "perft2" => {
                             let now = Instant::now();
                             let depth = parts.next().unwrap().parse::<u64>().unwrap();
-                            let mut cont = PerftMove{ depth_target: depth, depth: 0, counter: 0 };
+                            let mut cont = PerftDivide::new(depth);
                             gs.proc_movs(&mut cont);
-                            println!("total: {}", cont.counter);
+                            cont.print();
                             println!("{}ms", now.elapsed().as_millis());
                         }
                         "quit" => { break; }
@@ -1,14 +1,46 @@
-use std::fmt::Display;
+use core::{fmt::Display, hash::Hash};
+#[cfg(feature = "std")]
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hasher,
+};
+
+#[cfg(feature = "std")]
+use rand::{Rng, RngCore};
 
+use crate::piece::{ColouredPiece, Piece};
+#[cfg(feature = "std")]
 use crate::{
+    book::POLYGLOT_RANDOM,
     game::{ChessGame, Move},
-    notation::AlgebraicMove,
+    notation::{piece_letter, str_to_algebraic, AlgebraicMove, AlgebraicPosition},
+    piece::PlayerColour,
+    tt::TTable,
 };
 
+/// standard piece values in pawns, in `Piece` order
+pub const PIECE_VALUES: [i32; 6] = [1, 3, 3, 5, 9, 0];
+
+/// Bit index of the square on `file` (0 = a) and `rank` (0 = 1st rank).
+/// Bit 0 is h1 and indices run towards the a-file then up the board, so a1
+/// is 7, h8 is 56 and a8 is 63. `GameState` instead numbers a1 as 0.
+#[inline(always)]
+pub const fn square_index(file: u8, rank: u8) -> u8 {
+    rank * 8 + (7 - file)
+}
+
+/// (file, rank) of bit index `square`, the inverse of `square_index`
+#[inline(always)]
+pub const fn square_coords(square: u8) -> (u8, u8) {
+    (7 - (square & 7), square >> 3)
+}
+
 const LEFT_MASK: u64 = 0x8080808080808080;
 const RIGHT_MASK: u64 = 0x0101010101010101;
 const SIDE_MASK: u64 = LEFT_MASK | RIGHT_MASK;
+#[cfg(feature = "std")]
 const TOP_MASK: u64 = 0xFF00000000000000;
+#[cfg(feature = "std")]
 const BOT_MASK: u64 = 0x00000000000000FF;
 const EDGE_MASK: u64 = LEFT_MASK | RIGHT_MASK;
 
@@ -62,6 +94,83 @@ const fn calc_rook_vmove_map() -> [u64; 8] {
 const ROOK_VERT_MASKS: [u64; 8] = calc_rook_vmove_map();
 const ROOK_HOR_MASKS: [u64; 8] = calc_rook_hmove_map();
 
+/// (rank, index within rank) steps of the eight ray directions, the first
+/// four move to higher square indices and the last four to lower ones
+const RAY_STEPS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const ORTHO_RAYS: [usize; 4] = [0, 2, 4, 6];
+const DIAGONAL_RAYS: [usize; 4] = [1, 3, 5, 7];
+
+const fn calc_ray(square: u8, dir: usize) -> u64 {
+    let (rank_step, step) = RAY_STEPS[dir];
+    let mut rank = (square >> 3) as i8 + rank_step;
+    let mut index = (square & 0b111) as i8 + step;
+    let mut acc = 0;
+    while rank >= 0 && rank < 8 && index >= 0 && index < 8 {
+        acc |= 1 << (rank * 8 + index);
+        rank += rank_step;
+        index += step;
+    }
+    acc
+}
+
+const fn calc_ray_map() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut dir = 0;
+    while dir < 8 {
+        let mut square = 0;
+        while square < 64 {
+            table[dir][square] = calc_ray(square as u8, dir);
+            square += 1;
+        }
+        dir += 1;
+    }
+    table
+}
+
+/// every square from a square to the edge of the board in each direction
+const RAYS: [[u64; 64]; 8] = calc_ray_map();
+
+/// squares seen along ray `dir` from `square` up to and including the
+/// first of `blockers`
+#[inline(always)]
+const fn ray_attacks(square: u8, dir: usize, blockers: u64) -> u64 {
+    let ray = RAYS[dir][square as usize];
+    let blocked = ray & blockers;
+    if blocked == 0 {
+        return ray;
+    }
+    let first = if dir < 4 {
+        blocked.trailing_zeros()
+    } else {
+        63 - blocked.leading_zeros()
+    };
+    ray ^ RAYS[dir][first as usize]
+}
+
+/// squares seen from every square of `pieces` along the `dirs` rays
+#[inline(always)]
+const fn rays_attacks(mut pieces: u64, dirs: [usize; 4], blockers: u64) -> u64 {
+    let mut mask = 0;
+    while pieces != 0 {
+        let square = pieces.trailing_zeros() as u8;
+        mask |= ray_attacks(square, dirs[0], blockers)
+            | ray_attacks(square, dirs[1], blockers)
+            | ray_attacks(square, dirs[2], blockers)
+            | ray_attacks(square, dirs[3], blockers);
+        pieces &= pieces - 1;
+    }
+    mask
+}
+
 pub struct BBMove {
     /// 0b-pccvvvuuuyyyxxx
     /// xxx: from x
@@ -91,16 +200,100 @@ pub struct BoolExists<const _N: bool> {}
 /// ?101 => knight
 /// ?110 => --unused--
 /// ?111 => king
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct BitBoard {
     // Index that corresponds to each bit: 0b3210
     board: [u64; 4],
 }
 
+#[cfg(feature = "std")]
 #[derive(Clone)]
 pub struct BitBoardGameMove {
     pub mov: u16,
     bbg: BitBoardGame,
+    /// a castle, which in Chess960 is written as the king taking its rook so
+    /// the king need not end up on the target square
+    castle: bool,
+}
+
+/// inconsistency found by `BitBoard::validate`
+#[derive(Debug, PartialEq)]
+pub enum BoardError {
+    /// a square holding one of the unused codes `1000`, `0110` or `1110`
+    BadPiece { square: u8, nibble: u8 },
+    /// a side without exactly one king
+    KingCount { white: bool, count: u32 },
+}
+
+impl Display for BoardError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BoardError::BadPiece { square, nibble } => {
+                write!(f, "unused piece code {nibble:04b} on square {square}")
+            }
+            BoardError::KingCount { white, count } => {
+                let side = if *white { "white" } else { "black" };
+                write!(f, "{side} has {count} kings")
+            }
+        }
+    }
+}
+
+/// Masks from `BitBoard::gen_masks` shared by the piece move generators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenMasks {
+    /// `BitBoard::check_mask`
+    pub check: u64,
+    /// `BitBoard::hor_check_mask` and the other line check masks
+    pub hor_check: u64,
+    pub vert_check: u64,
+    pub lr_check: u64,
+    pub rl_check: u64,
+    /// `BitBoard::hor_pin_mask` and the other pin masks
+    pub hor_pins: u64,
+    pub ortho_pins: u64,
+    pub lr_pins: u64,
+    pub rl_pins: u64,
+    pub diagonal_pins: u64,
+    /// squares the other side attacks
+    pub enemy_attacks: u64,
+}
+
+/// state a null move discards, for `BitBoardGame::unmake_null`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NullUndo {
+    ep: Option<u8>,
+    halfmove: u16,
+    fullmove: u16,
+}
+
+/// a generated move that leaves the moving side in check
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq)]
+pub struct IllegalMove {
+    pub fen: String,
+    pub uci: String,
+}
+
+/// A finished game or one where a draw may be claimed, see
+/// `BitBoardGame::status`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Checkmate,
+    Stalemate,
+    /// neither side has the pieces left to mate
+    InsufficientMaterial,
+    /// the same position a fifth time, drawn without a claim
+    FivefoldRepetition,
+    /// 75 moves each without a capture or pawn move, drawn without a claim
+    SeventyFiveMoves,
+    /// the same position a third time, a draw either player may claim
+    ThreefoldRepetition,
+    /// 50 moves each without a capture or pawn move, a draw either player
+    /// may claim
+    FiftyMoves,
 }
 
 pub trait OnMove {
@@ -157,6 +350,18 @@ impl BitBoard {
     const LEFT2_SIDE: u64 = Self::LEFT_SIDE | (Self::LEFT_SIDE >> 1);
     const RIGHT2_SIDE: u64 = Self::RIGHT_SIDE | (Self::RIGHT_SIDE << 1);
 
+    /// a board with no pieces on it
+    pub const fn empty() -> Self {
+        Self { board: [0; 4] }
+    }
+
+    /// the board with `piece` placed on `square`, for building positions
+    /// without a FEN
+    pub fn with_piece(mut self, square: u8, piece: ColouredPiece) -> Self {
+        self.set(square, coloured_nibble(piece));
+        self
+    }
+
     /// set cell `square` to an empty cell
     #[inline(always)]
     pub fn clear(&mut self, square: u8) {
@@ -192,6 +397,45 @@ impl BitBoard {
             .for_each(|(i, v)| *v |= ((piece as u64 >> i) & 1) << square);
     }
 
+    /// Check every square holds a real piece code and each side has one king
+    pub fn validate(&self) -> Result<(), BoardError> {
+        for square in 0..64 {
+            let nibble = self.nibble(square);
+            if nibble == 0b1000 || nibble & 0b111 == 0b110 {
+                return Err(BoardError::BadPiece { square, nibble });
+            }
+        }
+        for white in [true, false] {
+            let count = self.col_king_mask(white).count_ones();
+            if count != 1 {
+                return Err(BoardError::KingCount { white, count });
+            }
+        }
+        Ok(())
+    }
+
+    /// the board seen from the other side, ranks reversed and colours swapped
+    #[inline(always)]
+    pub const fn flip_vertical(&self) -> BitBoard {
+        let [b0, b1, b2, b3] = self.board;
+        let (b0, b1, b2) = (b0.swap_bytes(), b1.swap_bytes(), b2.swap_bytes());
+        BitBoard {
+            board: [b0, b1, b2, (b0 | b1 | b2) & !b3.swap_bytes()],
+        }
+    }
+
+    /// the 4 bit piece code stored at `square`
+    #[inline(always)]
+    pub const fn nibble(&self, square: u8) -> u8 {
+        let mut nibble = 0;
+        let mut i = 0;
+        while i < 4 {
+            nibble |= (((self.board[i] >> square) & 1) as u8) << i;
+            i += 1;
+        }
+        nibble
+    }
+
     /// 1 if white
     /// 0 if black or no piece
     #[inline(always)]
@@ -340,12 +584,7 @@ impl BitBoard {
 
     #[inline(always)]
     pub const fn diagonal_like_attack_mask(&self, pieces: u64) -> u64 {
-        let blockers = self.piece_mask();
-        let ur = Self::sliding_mask::<true>(pieces, 7, blockers, Self::LEFT_SIDE);
-        let ul = Self::sliding_mask::<true>(pieces, 9, blockers, Self::RIGHT_SIDE);
-        let dr = Self::sliding_mask::<false>(pieces, 9, blockers, Self::LEFT_SIDE);
-        let dl = Self::sliding_mask::<false>(pieces, 7, blockers, Self::RIGHT_SIDE);
-        ur | ul | dr | dl
+        rays_attacks(pieces, DIAGONAL_RAYS, self.piece_mask())
     }
 
     /// colour 0 = white, u64::MAX = black
@@ -375,12 +614,7 @@ impl BitBoard {
 
     #[inline(always)]
     pub const fn ortho_like_attack_mask(&self, pieces: u64) -> u64 {
-        let blockers = self.piece_mask();
-        let r = Self::sliding_mask::<true>(pieces, 1, blockers, Self::RIGHT_SIDE);
-        let l = Self::sliding_mask::<false>(pieces, 1, blockers, Self::LEFT_SIDE);
-        let u = Self::sliding_mask::<true>(pieces, 8, blockers, 0);
-        let d = Self::sliding_mask::<false>(pieces, 8, blockers, 0);
-        r | l | u | d
+        rays_attacks(pieces, ORTHO_RAYS, self.piece_mask())
     }
 
     /// colour 0 = white, u64::MAX = black
@@ -414,9 +648,14 @@ impl BitBoard {
     #[inline(always)]
     pub const fn king_attack_mask(&self, turn: bool) -> u64 {
         let kings = self.col_king_mask(turn);
-        let u = kings << 8;
-        let d = kings >> 8;
-        let mast = kings | u | d;
+        self.king_like_attack_mask(kings)
+    }
+
+    #[inline(always)]
+    pub const fn king_like_attack_mask(&self, pieces: u64) -> u64 {
+        let u = pieces << 8;
+        let d = pieces >> 8;
+        let mast = pieces | u | d;
         ((mast >> 1) & !Self::LEFT_SIDE) | ((mast << 1) & !Self::RIGHT_SIDE) | u | d
     }
 
@@ -429,6 +668,128 @@ impl BitBoard {
             | self.king_attack_mask(turn)
     }
 
+    /// Every square a piece of colour `by_white` attacks, one bit per square
+    /// however many attackers it has. Squares holding that colour's own
+    /// pieces are included when they are defended, the same answer as
+    /// `is_attacked` for each square
+    #[inline(always)]
+    pub const fn attack_map(&self, by_white: bool) -> u64 {
+        self.attack_mask(by_white)
+    }
+
+    /// colour `by_white`'s own pieces that another of its pieces defends
+    #[inline(always)]
+    pub const fn defended_squares(&self, by_white: bool) -> u64 {
+        self.attack_map(by_white) & self.col_piece_mask(by_white)
+    }
+
+    /// true if any piece of colour `by_white` attacks `square`
+    #[inline(always)]
+    pub const fn is_attacked(&self, square: u8, by_white: bool) -> bool {
+        self.attack_mask(by_white) & (1 << square) != 0
+    }
+
+    /// mask of colour `by_white`'s pieces attacking `square`
+    #[inline(always)]
+    pub const fn attackers_to(&self, square: u8, by_white: bool) -> u64 {
+        // every piece type attacks back along the lines it attacks on, pawns
+        // from the opposite direction
+        let target = 1 << square;
+        (self.pawn_like_attack_mask(!by_white, target) & self.col_pawn_mask(by_white))
+            | (self.knight_like_attack_mask(target) & self.col_knight_mask(by_white))
+            | (self.diagonal_like_attack_mask(target) & self.col_diagonal_mask(by_white))
+            | (self.ortho_like_attack_mask(target) & self.col_ortho_mask(by_white))
+            | (self.king_like_attack_mask(target) & self.col_king_mask(by_white))
+    }
+
+    /// the enemy pieces checking colour `turn`'s king, two bits set is a
+    /// double check where only king moves are legal
+    #[inline(always)]
+    pub const fn checkers(&self, turn: bool) -> u64 {
+        let king = self.col_king_mask(turn);
+        if king == 0 {
+            return 0;
+        }
+        self.attackers_to(king.trailing_zeros() as u8, !turn)
+    }
+
+    /// true if colour `turn`'s king is attacked by two pieces at once
+    #[inline(always)]
+    pub const fn is_double_check(&self, turn: bool) -> bool {
+        self.checkers(turn).count_ones() > 1
+    }
+
+    /// how many pieces of colour `by_white` attack `square`
+    #[inline(always)]
+    pub const fn attack_count(&self, square: u8, by_white: bool) -> u32 {
+        self.attackers_to(square, by_white).count_ones()
+    }
+
+    /// Enemy attacks on colour `turn`'s king and the squares next to it,
+    /// an attacker counting once for every square of the zone it hits
+    pub const fn king_zone_pressure(&self, turn: bool) -> u32 {
+        let king = self.col_king_mask(turn);
+        let mut zone = king | self.king_like_attack_mask(king);
+        let mut pressure = 0;
+        while zone != 0 {
+            pressure += self.attack_count(zone.trailing_zeros() as u8, !turn);
+            zone &= zone - 1;
+        }
+        pressure
+    }
+
+    /// masks of colour `white`'s pawns, knights, bishops, rooks, queens and
+    /// king, in `Piece` order
+    #[inline(always)]
+    pub const fn piece_type_masks(&self, white: bool) -> [u64; 6] {
+        let diagonal = self.col_diagonal_mask(white);
+        let ortho = self.col_ortho_mask(white);
+        [
+            self.col_pawn_mask(white),
+            self.col_knight_mask(white),
+            diagonal & !ortho,
+            ortho & !diagonal,
+            diagonal & ortho,
+            self.col_king_mask(white),
+        ]
+    }
+
+    /// Static exchange evaluation of capturing on `to` with the piece on
+    /// `from` in centipawns for the capturing side, assuming both sides keep
+    /// recapturing with their least valuable attacker while it pays
+    /// Note: pins are ignored
+    pub fn see(&self, from: u8, to: u8) -> i32 {
+        let mut board = self.clone();
+        let mut gain = [0; 32];
+        let mut depth = 0;
+        let mut side = board.nibble(from) & 0b1000 == 0;
+        gain[0] = see_value(board.nibble(to));
+        let mut on_target = see_value(board.nibble(from));
+        board.mov(from, to);
+        while depth < gain.len() - 1 {
+            let attackers = board.attackers_to(to, side);
+            let cheapest = board
+                .piece_type_masks(side)
+                .into_iter()
+                .map(|pieces| pieces & attackers)
+                .find(|&pieces| pieces != 0);
+            let Some(pieces) = cheapest else {
+                break;
+            };
+            let next = pieces.trailing_zeros() as u8;
+            depth += 1;
+            gain[depth] = on_target - gain[depth - 1];
+            on_target = see_value(board.nibble(next));
+            board.mov(next, to);
+            side = !side;
+        }
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
     #[inline(always)]
     pub const fn hor_check_mask(&self, turn: bool) -> u64 {
         let kings = self.col_king_mask(turn);
@@ -513,14 +874,23 @@ impl BitBoard {
         mask
     }
 
+    /// Squares a non-king move of side `turn` may end on without leaving its
+    /// king in check. `u64::MAX` when not in check, the checker and any
+    /// squares between it and the king for a single check and 0 for double
+    /// check.
     #[inline(always)]
     pub const fn check_mask(&self, turn: bool) -> u64 {
-        let mut mask = u64::MAX;
-        mask &= self.hor_check_mask(turn)
+        self.hor_check_mask(turn)
             & self.vert_check_mask(turn)
             & self.lr_check_mask(turn)
-            & self.rl_check_mask(turn);
+            & self.rl_check_mask(turn)
+            & self.contact_check_mask(turn)
+    }
 
+    /// the part of `check_mask` from knights and pawns
+    #[inline(always)]
+    const fn contact_check_mask(&self, turn: bool) -> u64 {
+        let mut mask = u64::MAX;
         let kings = self.col_king_mask(turn);
 
         let other_knights = Self::col_knight_mask(self, !turn);
@@ -619,6 +989,17 @@ impl BitBoard {
         mask
     }
 
+    /// true if colour `turn` taking en passant from `from` to `to` opens a
+    /// diagonal onto its own king by removing the captured pawn, a pin the
+    /// pin masks miss as the pinned pawn isn't the one moving
+    #[inline(always)]
+    fn ep_opens_diagonal(&self, turn: bool, from: u8, to: u8) -> bool {
+        let mut after = self.clone();
+        after.mov(from, to);
+        after.clear(if turn { to - 8 } else { to + 8 });
+        after.diagonal_attack_mask(!turn) & after.col_king_mask(turn) != 0
+    }
+
     // Horizontal pin that goes through two pieces
     #[inline(always)]
     pub const fn hor_pin_mask2(&self, turn: bool) -> u64 {
@@ -695,6 +1076,62 @@ impl BitBoard {
         self.lr_pin_mask(turn) | self.rl_pin_mask(turn)
     }
 
+    /// pieces of side `turn` pinned to their king
+    pub const fn pinned_pieces(&self, turn: bool) -> u64 {
+        (self.ortho_pin_mask(turn) | self.diagonal_pin_mask(turn)) & self.col_piece_mask(turn)
+    }
+
+    /// The squares the piece of side `turn` on `square` can still move
+    /// along, from next to its king up to and including the pinning piece.
+    /// 0 if the piece isn't pinned
+    pub const fn pin_ray(&self, square: u8, turn: bool) -> u64 {
+        let bit = 1 << square;
+        if self.pinned_pieces(turn) & bit == 0 {
+            return 0;
+        }
+        let king = self.col_king_mask(turn).trailing_zeros() as usize;
+        let mut dir = 0;
+        while dir < 8 {
+            let ray = RAYS[dir][king];
+            if ray & bit != 0 {
+                let pins = if dir % 2 == 0 {
+                    self.ortho_pin_mask(turn)
+                } else {
+                    self.diagonal_pin_mask(turn)
+                };
+                return ray & pins;
+            }
+            dir += 1;
+        }
+        0
+    }
+
+    /// The check, pin and attack masks for side `turn` that the piece move
+    /// generators share, computed once per position
+    #[inline(always)]
+    pub const fn gen_masks(&self, turn: bool) -> GenMasks {
+        let hor_check = self.hor_check_mask(turn);
+        let vert_check = self.vert_check_mask(turn);
+        let lr_check = self.lr_check_mask(turn);
+        let rl_check = self.rl_check_mask(turn);
+        let hor_pins = self.hor_pin_mask(turn);
+        let lr_pins = self.lr_pin_mask(turn);
+        let rl_pins = self.rl_pin_mask(turn);
+        GenMasks {
+            check: hor_check & vert_check & lr_check & rl_check & self.contact_check_mask(turn),
+            hor_check,
+            vert_check,
+            lr_check,
+            rl_check,
+            hor_pins,
+            ortho_pins: hor_pins | self.vert_pin_mask(turn),
+            lr_pins,
+            rl_pins,
+            diagonal_pins: lr_pins | rl_pins,
+            enemy_attacks: self.attack_mask(!turn),
+        }
+    }
+
     #[inline(always)]
     pub fn gen_pawn_moves<
         const WQ: bool,
@@ -707,13 +1144,14 @@ impl BitBoard {
         turn: bool,
         on_move: &mut Mov,
         sq: Option<u8>,
+        masks: &GenMasks,
     ) {
-        let check_mask = self.check_mask(turn);
-        let hor_pins = self.hor_pin_mask(turn);
-        let ortho_pins = self.ortho_pin_mask(turn);
-        let lr_pins = self.lr_pin_mask(turn);
-        let rl_pins = self.rl_pin_mask(turn);
-        let diagonal_pins = self.diagonal_pin_mask(turn);
+        let check_mask = masks.check;
+        let hor_pins = masks.hor_pins;
+        let ortho_pins = masks.ortho_pins;
+        let lr_pins = masks.lr_pins;
+        let rl_pins = masks.rl_pins;
+        let diagonal_pins = masks.diagonal_pins;
         let empty = !self.piece_mask();
         let empty_free = empty & check_mask;
         let ep = match sq {
@@ -809,6 +1247,7 @@ impl BitBoard {
                     }
                 } else if self.hor_pin_mask2(turn) & (0xff << (8 * 4)) == 0
                     && check_mask & (1 << (from_idx - 1)) != 0
+                    && !self.ep_opens_diagonal(turn, from_idx, from_idx + 7)
                 {
                     on_move.on_ep_move::<WQ, WK, BQ, BK>(turn, self, from_idx, from_idx + 7);
                 }
@@ -851,6 +1290,7 @@ impl BitBoard {
                     }
                 } else if self.hor_pin_mask2(turn) & (0xff << (8 * 4)) == 0
                     && check_mask & (1 << (from_idx + 1)) != 0
+                    && !self.ep_opens_diagonal(turn, from_idx, from_idx + 9)
                 {
                     on_move.on_ep_move::<WQ, WK, BQ, BK>(turn, self, from_idx, from_idx + 9);
                 }
@@ -939,6 +1379,7 @@ impl BitBoard {
                     }
                 } else if self.hor_pin_mask2(turn) & (0xff << (8 * 3)) == 0
                     && check_mask & (1 << (from_idx + 1)) != 0
+                    && !self.ep_opens_diagonal(turn, from_idx, from_idx - 7)
                 {
                     on_move.on_ep_move::<WQ, WK, BQ, BK>(turn, self, from_idx, from_idx - 7);
                 }
@@ -981,6 +1422,7 @@ impl BitBoard {
                     }
                 } else if self.hor_pin_mask2(turn) & (0xff << (8 * 3)) == 0
                     && check_mask & (1 << (from_idx - 1)) != 0
+                    && !self.ep_opens_diagonal(turn, from_idx, from_idx - 9)
                 {
                     on_move.on_ep_move::<WQ, WK, BQ, BK>(turn, self, from_idx, from_idx - 9);
                 }
@@ -1000,10 +1442,11 @@ impl BitBoard {
         &self,
         turn: bool,
         on_move: &mut Mov,
+        masks: &GenMasks,
     ) {
-        let base_mask = self.enemy_or_empty(turn) & self.check_mask(turn);
-        let ortho_pins = self.ortho_pin_mask(turn);
-        let diagonal_pins = self.diagonal_pin_mask(turn);
+        let base_mask = self.enemy_or_empty(turn) & masks.check;
+        let ortho_pins = masks.ortho_pins;
+        let diagonal_pins = masks.diagonal_pins;
 
         let mut knights = self.col_knight_mask(turn) & !ortho_pins & !diagonal_pins;
         while knights != 0 {
@@ -1029,10 +1472,11 @@ impl BitBoard {
         &self,
         turn: bool,
         on_move: &mut Mov,
+        masks: &GenMasks,
     ) {
-        let base_mask = self.enemy_or_empty(turn) & self.check_mask(turn);
-        let ortho_pins = self.ortho_pin_mask(turn);
-        let diagonal_pins = self.diagonal_pin_mask(turn);
+        let base_mask = self.enemy_or_empty(turn) & masks.check;
+        let ortho_pins = masks.ortho_pins;
+        let diagonal_pins = masks.diagonal_pins;
 
         let mut free_bishops = self.col_diagonal_mask(turn) & !diagonal_pins & !ortho_pins;
         let mut pin_bishops = self.col_diagonal_mask(turn) & diagonal_pins;
@@ -1072,10 +1516,11 @@ impl BitBoard {
         &self,
         turn: bool,
         on_move: &mut Mov,
+        masks: &GenMasks,
     ) {
-        let base_mask = self.enemy_or_empty(turn) & self.check_mask(turn);
-        let ortho_pins = self.ortho_pin_mask(turn);
-        let diagonal_pins = self.diagonal_pin_mask(turn);
+        let base_mask = self.enemy_or_empty(turn) & masks.check;
+        let ortho_pins = masks.ortho_pins;
+        let diagonal_pins = masks.diagonal_pins;
 
         let mut free_rooks = self.col_ortho_mask(turn) & !diagonal_pins & !ortho_pins;
         let mut pin_rooks = self.col_ortho_mask(turn) & ortho_pins;
@@ -1114,30 +1559,31 @@ impl BitBoard {
         &self,
         turn: bool,
         on_move: &mut Mov,
+        masks: &GenMasks,
     ) {
         let empty = !self.piece_mask();
-        let other_attacks = self.attack_mask(!turn);
+        let other_attacks = masks.enemy_attacks;
         let base_mask = self.enemy_or_empty(turn) & !other_attacks;
         let king = self.col_king_mask(turn);
 
         let from_idx = king.trailing_zeros() as u8;
         let mut to_mask = self.king_attack_mask(turn) & base_mask;
 
-        if self.hor_check_mask(turn) != u64::MAX {
+        if masks.hor_check != u64::MAX {
             to_mask &= !((!Self::LEFT_SIDE & (king >> 1) | !Self::RIGHT_SIDE & (king << 1))
                 & !self.col_ortho_mask(!turn));
         }
 
-        if self.vert_check_mask(turn) != u64::MAX {
+        if masks.vert_check != u64::MAX {
             to_mask &= !(((king >> 8) | (king << 8)) & !self.col_ortho_mask(!turn));
         }
 
-        if self.lr_check_mask(turn) != u64::MAX {
+        if masks.lr_check != u64::MAX {
             to_mask &= !((!Self::RIGHT_SIDE & (king >> 7) | !Self::LEFT_SIDE & (king << 7))
                 & !self.col_diagonal_mask(!turn));
         }
 
-        if self.rl_check_mask(turn) != u64::MAX {
+        if masks.rl_check != u64::MAX {
             to_mask &= !((!Self::LEFT_SIDE & (king >> 9) | !Self::RIGHT_SIDE & (king << 9))
                 & !self.col_diagonal_mask(!turn));
         }
@@ -1178,14 +1624,114 @@ impl BitBoard {
         on_move: &mut Mov,
         ep: Option<u8>,
     ) {
-        self.gen_pawn_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, ep);
-        self.gen_knight_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move);
-        self.gen_diagonal_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move);
-        self.gen_ortho_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move);
-        self.gen_king_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move);
+        let masks = self.gen_masks(turn);
+        self.gen_pawn_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, ep, &masks);
+        self.gen_knight_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, &masks);
+        self.gen_diagonal_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, &masks);
+        self.gen_ortho_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, &masks);
+        self.gen_king_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, &masks);
+    }
+
+    /// `gen_moves` with the castling rights (white queenside, white kingside,
+    /// black queenside, black kingside) chosen at runtime. Each arm calls an
+    /// out of line copy so the sixteen `gen_moves` bodies don't share one
+    /// stack frame, which overflows the stack in unoptimised builds
+    pub fn gen_moves_dyn<Mov: OnMove>(
+        &self,
+        turn: bool,
+        on_move: &mut Mov,
+        ep: Option<u8>,
+        rights: (bool, bool, bool, bool),
+    ) {
+        match rights {
+            (true, true, true, true) => {
+                self.gen_moves_outlined::<true, true, true, true, Mov>(turn, on_move, ep)
+            }
+            (true, true, true, false) => {
+                self.gen_moves_outlined::<true, true, true, false, Mov>(turn, on_move, ep)
+            }
+            (true, true, false, true) => {
+                self.gen_moves_outlined::<true, true, false, true, Mov>(turn, on_move, ep)
+            }
+            (true, true, false, false) => {
+                self.gen_moves_outlined::<true, true, false, false, Mov>(turn, on_move, ep)
+            }
+            (true, false, true, true) => {
+                self.gen_moves_outlined::<true, false, true, true, Mov>(turn, on_move, ep)
+            }
+            (true, false, true, false) => {
+                self.gen_moves_outlined::<true, false, true, false, Mov>(turn, on_move, ep)
+            }
+            (true, false, false, true) => {
+                self.gen_moves_outlined::<true, false, false, true, Mov>(turn, on_move, ep)
+            }
+            (true, false, false, false) => {
+                self.gen_moves_outlined::<true, false, false, false, Mov>(turn, on_move, ep)
+            }
+            (false, true, true, true) => {
+                self.gen_moves_outlined::<false, true, true, true, Mov>(turn, on_move, ep)
+            }
+            (false, true, true, false) => {
+                self.gen_moves_outlined::<false, true, true, false, Mov>(turn, on_move, ep)
+            }
+            (false, true, false, true) => {
+                self.gen_moves_outlined::<false, true, false, true, Mov>(turn, on_move, ep)
+            }
+            (false, true, false, false) => {
+                self.gen_moves_outlined::<false, true, false, false, Mov>(turn, on_move, ep)
+            }
+            (false, false, true, true) => {
+                self.gen_moves_outlined::<false, false, true, true, Mov>(turn, on_move, ep)
+            }
+            (false, false, true, false) => {
+                self.gen_moves_outlined::<false, false, true, false, Mov>(turn, on_move, ep)
+            }
+            (false, false, false, true) => {
+                self.gen_moves_outlined::<false, false, false, true, Mov>(turn, on_move, ep)
+            }
+            (false, false, false, false) => {
+                self.gen_moves_outlined::<false, false, false, false, Mov>(turn, on_move, ep)
+            }
+        }
+    }
+
+    /// `gen_moves` as its own function for `gen_moves_dyn` to dispatch to
+    #[inline(never)]
+    fn gen_moves_outlined<
+        const WQ: bool,
+        const WK: bool,
+        const BQ: bool,
+        const BK: bool,
+        Mov: OnMove,
+    >(
+        &self,
+        turn: bool,
+        on_move: &mut Mov,
+        ep: Option<u8>,
+    ) {
+        self.gen_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, ep);
+    }
+
+    /// generate moves in a position where a pawn of the side not moving has
+    /// just double pushed over `ep`
+    #[inline(always)]
+    pub fn gen_moves_with_ep<
+        const WQ: bool,
+        const WK: bool,
+        const BQ: bool,
+        const BK: bool,
+        Mov: OnMove,
+    >(
+        &self,
+        turn: bool,
+        on_move: &mut Mov,
+        ep: u8,
+    ) {
+        self.gen_moves::<WQ, WK, BQ, BK, Mov>(turn, on_move, Some(ep));
     }
 }
 
+#[cfg(feature = "std")]
 #[derive(Clone)]
 pub struct BitBoardGame {
     pub board: BitBoard,
@@ -1195,8 +1741,18 @@ pub struct BitBoardGame {
     black_qs: bool,
     black_ks: bool,
     ep: Option<u8>,
+    /// plies since the last capture or pawn move
+    halfmove: u16,
+    fullmove: u16,
+    /// Chess960 (queenside, kingside) rook start squares within the back
+    /// rank, None for standard chess
+    castle_files: Option<(u8, u8)>,
+    /// white's `material` less black's, set on each generated successor
+    /// and by `do_move` rather than counted
+    material: i16,
 }
 
+#[cfg(feature = "std")]
 impl ChessGame for BitBoardGame {
     type Move = BitBoardGameMove;
 
@@ -1207,92 +1763,36 @@ impl ChessGame for BitBoardGame {
     }
 
     fn from_fen(fen: &str) -> Option<Self> {
-        let mut fen_parts = fen.trim().split(" ");
-        let fenboard = fen_parts.next()?;
-        let turn = match fen_parts.next()? {
-            "w" => true,
-            "b" => false,
-            _ => return None,
-        };
-
-        let castle_rights = fen_parts.next()?;
-        let white_ks_castle = castle_rights.contains('K');
-        let white_qs_castle = castle_rights.contains('Q');
-        let black_ks_castle = castle_rights.contains('k');
-        let black_qs_castle = castle_rights.contains('q');
-
-        let enpassant_col = match fen_parts.next()?.chars().next()? {
-            'a' => Some(7),
-            'b' => Some(6),
-            'c' => Some(5),
-            'd' => Some(4),
-            'e' => Some(3),
-            'f' => Some(2),
-            'g' => Some(1),
-            'h' => Some(0),
-            _ => None,
-        };
-
-        let enpassant = match enpassant_col {
-            Some(x) => Some(if turn { x + 40 } else { x + 16 }),
-            _ => None,
-        };
-
-        let mut board: [u64; 4] = [0; 4];
-        let mut counter = 0;
-        for c in fenboard.replace('/', "").chars() {
-            if c.is_digit(10) {
-                counter += c.to_digit(10)?;
-                continue;
-            }
-
-            let mut piece_idx = match c.to_ascii_uppercase() {
-                'P' => 0b100,
-                'N' => 0b101,
-                'B' => 0b001,
-                'R' => 0b010,
-                'Q' => 0b011,
-                'K' => 0b111,
-                _ => return None,
-            };
-            piece_idx |= if c.is_ascii_uppercase() { 0b1000 } else { 0 };
-            board
-                .iter_mut()
-                .enumerate()
-                .for_each(|(i, v)| *v |= ((piece_idx >> i) & 1) << (63 - counter));
-            counter += 1;
-        }
-
-        if counter == 64 {
-            Some(BitBoardGame {
-                board: BitBoard { board },
-                turn,
-                white_qs: white_qs_castle,
-                white_ks: white_ks_castle,
-                black_qs: black_qs_castle,
-                black_ks: black_ks_castle,
-                ep: enpassant,
-            })
-        } else {
-            None
-        }
+        Self::parse_fen(fen, false)
     }
 
-    fn decode_alg(&mut self, _mov: &AlgebraicMove) -> Self::Move {
-        todo!()
+    fn decode_alg(&mut self, mov: &AlgebraicMove) -> Self::Move {
+        self.move_from_algebraic(mov)
+            .expect("algebraic move is not legal here")
     }
 
     fn moves(&self) -> Vec<Self::Move> {
-        let mut genny = GenericMoveGenerator {
-            next: Vec::with_capacity(240),
-        };
-        self.proc_movs(&mut genny);
-        genny.next
+        let mut next = Vec::with_capacity(240);
+        self.gen_into(&mut next);
+        next
     }
 
     fn do_move(&mut self, mov: &Self::Move) -> Self::UnMove {
         let un = self.clone();
         *self = mov.clone().bbg;
+        let gain = un.material_gain(mov);
+        self.material = if un.turn {
+            un.material + gain
+        } else {
+            un.material - gain
+        };
+        debug_assert_eq!(self.board.validate(), Ok(()), "{}", mov.to_uci());
+        debug_assert_eq!(
+            self.incremental_material(),
+            self.material(true) - self.material(false),
+            "{}",
+            mov.to_uci()
+        );
         un
     }
 
@@ -1300,166 +1800,1512 @@ impl ChessGame for BitBoardGame {
         *self = mov.clone()
     }
 
-    fn gen_alg(&mut self, _mov: &Self::Move) -> AlgebraicMove {
-        todo!()
+    fn gen_alg(&mut self, mov: &Self::Move) -> AlgebraicMove {
+        self.algebraic(mov)
     }
 }
 
-impl BitBoardGame {
-    pub fn proc_movs<MOV: OnMove>(&self, mov: &mut MOV) {
-        let turn = self.turn;
-        match (self.white_qs, self.white_ks, self.black_qs, self.black_ks) {
-            (true, true, true, true) => self
-                .board
-                .gen_moves::<true, true, true, true, MOV>(turn, mov, self.ep),
-            (true, true, true, false) => self
-                .board
-                .gen_moves::<true, true, true, false, MOV>(turn, mov, self.ep),
-            (true, true, false, true) => self
-                .board
-                .gen_moves::<true, true, false, true, MOV>(turn, mov, self.ep),
-            (true, true, false, false) => self
-                .board
-                .gen_moves::<true, true, false, false, MOV>(turn, mov, self.ep),
-            (true, false, true, true) => self
-                .board
-                .gen_moves::<true, false, true, true, MOV>(turn, mov, self.ep),
-            (true, false, true, false) => self
-                .board
-                .gen_moves::<true, false, true, false, MOV>(turn, mov, self.ep),
-            (true, false, false, true) => self
-                .board
-                .gen_moves::<true, false, false, true, MOV>(turn, mov, self.ep),
-            (true, false, false, false) => self
-                .board
-                .gen_moves::<true, false, false, false, MOV>(turn, mov, self.ep),
-            (false, true, true, true) => self
-                .board
-                .gen_moves::<false, true, true, true, MOV>(turn, mov, self.ep),
-            (false, true, true, false) => self
-                .board
-                .gen_moves::<false, true, true, false, MOV>(turn, mov, self.ep),
-            (false, true, false, true) => self
-                .board
-                .gen_moves::<false, true, false, true, MOV>(turn, mov, self.ep),
-            (false, true, false, false) => self
-                .board
-                .gen_moves::<false, true, false, false, MOV>(turn, mov, self.ep),
-            (false, false, true, true) => self
-                .board
-                .gen_moves::<false, false, true, true, MOV>(turn, mov, self.ep),
-            (false, false, true, false) => self
-                .board
-                .gen_moves::<false, false, true, false, MOV>(turn, mov, self.ep),
-            (false, false, false, true) => self
-                .board
-                .gen_moves::<false, false, false, true, MOV>(turn, mov, self.ep),
-            (false, false, false, false) => self
-                .board
-                .gen_moves::<false, false, false, false, MOV>(turn, mov, self.ep),
-        }
+/// Positions compare equal when they are the same for the rules of
+/// repetition: board, side to move, castling rights and en passant. The move
+/// clocks are ignored and an en passant square only counts when a pawn of the
+/// side to move could capture onto it, so 1. e4 Nf6 2. Nf3 and 1. Nf3 Nf6
+/// 2. e4 are the same position.
+#[cfg(feature = "std")]
+impl PartialEq for BitBoardGame {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.turn == other.turn
+            && self.castling_rights() == other.castling_rights()
+            && self.capturable_ep() == other.capturable_ep()
+            && self.castle_files == other.castle_files
     }
 }
 
-pub fn print_bitmask(mask: u64) {
-    let mut bstr = String::from("");
-    for i in 0..64 {
-        if i % 8 == 0 {
-            bstr.push('\n')
-        }
-        bstr.push(if (mask >> 63 - i) & 1 == 0 { '-' } else { 'X' });
+#[cfg(feature = "std")]
+impl Eq for BitBoardGame {}
+
+#[cfg(feature = "std")]
+impl Hash for BitBoardGame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.turn.hash(state);
+        self.castling_rights().hash(state);
+        self.capturable_ep().hash(state);
+        self.castle_files.hash(state);
     }
-    println!("{:b}", mask);
-    println!("{}", bstr);
 }
 
-impl Display for BitBoard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut bstr = String::from("");
+/// serialized as its FEN string
+#[cfg(feature = "serde")]
+impl serde::Serialize for BitBoardGame {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BitBoardGame {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        Self::from_fen(&fen)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid FEN \"{fen}\"")))
+    }
+}
+
+#[cfg(feature = "std")]
+impl BitBoardGame {
+    pub fn proc_movs<MOV: OnMove>(&self, mov: &mut MOV) {
+        self.board
+            .gen_moves_dyn(self.turn, mov, self.ep, self.castling_rights());
+    }
+
+    /// the same position with colours swapped, white playing up the board as
+    /// black did
+    pub fn flipped(&self) -> Self {
+        Self {
+            board: self.board.flip_vertical(),
+            turn: !self.turn,
+            white_qs: self.black_qs,
+            white_ks: self.black_ks,
+            black_qs: self.white_qs,
+            black_ks: self.white_ks,
+            ep: self.ep.map(|ep| ep ^ 56),
+            material: -self.material,
+            ..self.clone()
+        }
+    }
+
+    /// Legal moves written into `buf`, replacing its contents, without
+    /// allocating
+    pub fn moves_into(&self, buf: &mut MoveList) {
+        buf.clear();
+        self.gen_into(buf);
+    }
+
+    /// Legal moves written into `buf`, replacing its contents. The
+    /// generator itself holds no storage, so a `Vec` kept by the caller
+    /// across calls only allocates when it has to grow
+    pub fn moves_reuse(&self, buf: &mut Vec<BitBoardGameMove>) {
+        buf.clear();
+        self.gen_into(buf);
+    }
+
+    /// A game from a built board, `castling` as in `castle_rights` and `ep`
+    /// the square index behind a pawn that just moved two
+    pub fn from_board(
+        board: BitBoard,
+        turn: bool,
+        castling: (bool, bool, bool, bool),
+        ep: Option<u8>,
+    ) -> Self {
+        let (white_qs, white_ks, black_qs, black_ks) = castling;
+        let mut game = Self::from_parts(board, turn, white_qs, white_ks, black_qs, black_ks, ep);
+        game.material = (game.material(true) - game.material(false)) as i16;
+        game
+    }
+
+    /// the side to move
+    pub const fn turn(&self) -> PlayerColour {
+        if self.turn {
+            PlayerColour::White
+        } else {
+            PlayerColour::Black
+        }
+    }
+
+    /// (white queenside, white kingside, black queenside, black kingside)
+    pub const fn castling_rights(&self) -> (bool, bool, bool, bool) {
+        (self.white_qs, self.white_ks, self.black_qs, self.black_ks)
+    }
+
+    /// true if the side to move is in check, i.e. its `check_mask` is not
+    /// the unrestricted `u64::MAX`
+    pub const fn in_check(&self) -> bool {
+        self.board.check_mask(self.turn) != u64::MAX
+    }
+
+    /// true if the side to move has no legal moves and isn't in check
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && self.moves().is_empty()
+    }
+
+    /// true if the side to move has no legal moves and is in check
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.moves().is_empty()
+    }
+
+    /// true if neither side can ever mate: only kings, or kings and a single
+    /// knight or bishop, or kings and bishops all on squares of one colour
+    pub fn is_insufficient_material(&self) -> bool {
+        const DARK_SQUARES: u64 = 0x55aa55aa55aa55aa;
+        let board = &self.board;
+        let [white, black] = [true, false].map(|white| board.piece_type_masks(white));
+        let heavy = |masks: [u64; 6]| masks[0] | masks[3] | masks[4];
+        if heavy(white) | heavy(black) != 0 {
+            return false;
+        }
+        let knights = white[1] | black[1];
+        let bishops = white[2] | black[2];
+        (knights | bishops).count_ones() <= 1
+            || (knights == 0 && (bishops & DARK_SQUARES == 0 || bishops & !DARK_SQUARES == 0))
+    }
+
+    /// How the game stands given the Polyglot hashes of the positions before
+    /// this one, checkmate and the draws that need no claim first. None
+    /// while the game goes on without a draw to claim
+    pub fn status(&self, history: &[u64]) -> Option<GameStatus> {
+        if self.is_checkmate() {
+            return Some(GameStatus::Checkmate);
+        }
+        if let Some(draw) = self.auto_draw(history) {
+            return Some(draw);
+        }
+        if self.repetitions(history) >= 3 {
+            Some(GameStatus::ThreefoldRepetition)
+        } else if self.halfmove >= 100 {
+            Some(GameStatus::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// Only the draws that end the game without a claim: stalemate,
+    /// insufficient material, fivefold repetition and the 75 move rule.
+    /// A checkmate is never a draw, even on the 75th move
+    pub fn auto_draw(&self, history: &[u64]) -> Option<GameStatus> {
+        if self.moves().is_empty() {
+            return (!self.in_check()).then_some(GameStatus::Stalemate);
+        }
+        if self.is_insufficient_material() {
+            Some(GameStatus::InsufficientMaterial)
+        } else if self.repetitions(history) >= 5 {
+            Some(GameStatus::FivefoldRepetition)
+        } else if self.halfmove >= 150 {
+            Some(GameStatus::SeventyFiveMoves)
+        } else {
+            None
+        }
+    }
+
+    /// times this position has occurred, counting this one
+    fn repetitions(&self, history: &[u64]) -> usize {
+        let hash = self.polyglot_hash();
+        1 + history.iter().filter(|&&earlier| earlier == hash).count()
+    }
+
+    /// Pass the turn to the other side, for null move pruning. Castling
+    /// rights are kept and en passant is lost. Must not be used when
+    /// `in_check`, the opponent could then take the king.
+    pub fn make_null(&mut self) -> NullUndo {
+        let undo = NullUndo {
+            ep: self.ep,
+            halfmove: self.halfmove,
+            fullmove: self.fullmove,
+        };
+        self.ep = None;
+        self.halfmove += 1;
+        if !self.turn {
+            self.fullmove += 1;
+        }
+        self.turn = !self.turn;
+        undo
+    }
+
+    /// Take back a `make_null`
+    pub fn unmake_null(&mut self, undo: NullUndo) {
+        self.turn = !self.turn;
+        self.ep = undo.ep;
+        self.halfmove = undo.halfmove;
+        self.fullmove = undo.fullmove;
+    }
+
+    /// the square behind a pawn that just moved two, whether or not it can be
+    /// taken
+    pub const fn ep_square(&self) -> Option<u8> {
+        self.ep
+    }
+
+    /// the en passant square if a pawn of the side to move attacks it
+    pub fn capturable_ep(&self) -> Option<u8> {
+        self.ep
+            .filter(|ep| self.board.pawn_attack_mask(self.turn) & (1 << ep) != 0)
+    }
+
+    /// plies since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove
+    }
+
+    /// Play UCI moves in order, stopping at the first one that isn't legal
+    /// and returning its index. The moves before it stay played
+    pub fn play_uci(&mut self, moves: &[&str]) -> Result<(), usize> {
+        for (i, uci) in moves.iter().enumerate() {
+            match self.move_from_uci(uci) {
+                Some(mov) => *self = mov.bbg,
+                None => return Err(i),
+            }
+        }
+        Ok(())
+    }
+
+    /// the legal move written as `uci`, None if there isn't one
+    pub fn move_from_uci(&self, uci: &str) -> Option<BitBoardGameMove> {
+        self.moves().into_iter().find(|mov| mov.to_uci() == uci)
+    }
+
+    /// The legal move described by `alg`, None if there is no such move or
+    /// the description fits more than one
+    pub fn move_from_algebraic(&self, alg: &AlgebraicMove) -> Option<BitBoardGameMove> {
+        let (origin, target, promotion) = match alg {
+            AlgebraicMove::KSCastle | AlgebraicMove::QSCastle => {
+                return self
+                    .moves()
+                    .into_iter()
+                    .find(|mov| mov.castle && self.algebraic(mov) == *alg);
+            }
+            AlgebraicMove::Move(origin, target) => (origin, target, None),
+            AlgebraicMove::Promotion(origin, target, promo) => (origin, target, Some(*promo)),
+        };
+        let AlgebraicPosition::Square(to_rank, to_file) = *target else {
+            return None;
+        };
+        let (piece, file, rank) = match *origin {
+            AlgebraicPosition::Piece(piece) => (piece, None, None),
+            AlgebraicPosition::FilePiece(file, piece) => (piece, Some(file), None),
+            AlgebraicPosition::RankPiece(rank, piece) => (piece, None, Some(rank)),
+            AlgebraicPosition::SquarePiece(rank, file, piece) => (piece, Some(file), Some(rank)),
+            AlgebraicPosition::Square(..) => return None,
+        };
+        let to = square_index(to_file, to_rank);
+        let mut matching = self.moves().into_iter().filter(|mov| {
+            let (from_file, from_rank) = square_coords((mov.mov & 63) as u8);
+            !mov.castle
+                && ((mov.mov >> 6) & 63) as u8 == to
+                && mov.moving_piece() == piece
+                && mov.promotion() == promotion
+                && file.is_none_or(|file| file == from_file)
+                && rank.is_none_or(|rank| rank == from_rank)
+        });
+        let mov = matching.next()?;
+        matching.next().is_none().then_some(mov)
+    }
+
+    /// Whether `mov` is legal here, checking that one move rather than
+    /// generating them all. A move generated for a different position is
+    /// not legal here and gives false
+    pub fn is_legal(&self, mov: &BitBoardGameMove) -> bool {
+        let from = (mov.mov & 63) as u8;
+        let to = ((mov.mov >> 6) & 63) as u8;
+        let promotion = mov.mov & (1 << 14) != 0;
+        let board = &self.board;
+        let own = board.col_piece_mask(self.turn);
+        if mov.bbg.turn == self.turn || own & (1 << from) == 0 {
+            return false;
+        }
+        let nibble = board.nibble(from);
+        // castles, the king moving two squares or onto its own rook
+        if nibble & 0b111 == 0b111 && (own & (1 << to) != 0 || from.abs_diff(to) == 2) {
+            return self.moves().iter().any(|m| {
+                m.mov == mov.mov
+                    && m.bbg == mov.bbg
+                    && (m.bbg.ep, m.bbg.halfmove, m.bbg.fullmove)
+                        == (mov.bbg.ep, mov.bbg.halfmove, mov.bbg.fullmove)
+            });
+        }
+
+        let piece = 1 << from;
+        let empty = !board.piece_mask();
+        let reach = match nibble & 0b111 {
+            0b100 => {
+                let ep = self.ep.map_or(0, |ep| 1 << ep);
+                let captures = board.pawn_like_attack_mask(self.turn, piece)
+                    & (board.col_piece_mask(!self.turn) | ep);
+                let pushes = if self.turn {
+                    let single = (piece << 8) & empty;
+                    single | (((single & 0xff << 16) << 8) & empty)
+                } else {
+                    let single = (piece >> 8) & empty;
+                    single | (((single & 0xff << 40) >> 8) & empty)
+                };
+                captures | pushes
+            }
+            0b101 => board.knight_like_attack_mask(piece),
+            0b001 => board.diagonal_like_attack_mask(piece),
+            0b010 => board.ortho_like_attack_mask(piece),
+            0b011 => board.diagonal_like_attack_mask(piece) | board.ortho_like_attack_mask(piece),
+            _ => board.king_like_attack_mask(piece),
+        };
+        if reach & !own & (1 << to) == 0 {
+            return false;
+        }
+
+        let mut expected = board.clone();
+        expected.mov(from, to);
+        if nibble & 0b111 == 0b100 {
+            if self.ep == Some(to) {
+                expected.clear(if self.turn { to - 8 } else { to + 8 });
+            }
+            if promotion == (8..56).contains(&to) {
+                return false;
+            }
+            if promotion {
+                let code = [0b101, 0b001, 0b010, 0b011][((mov.mov >> 12) & 3) as usize];
+                expected.set(to, code | (nibble & 0b1000));
+            }
+        } else if promotion {
+            return false;
+        }
+        if expected != mov.bbg.board || !self.is_successor_state(from, to, nibble, &mov.bbg) {
+            return false;
+        }
+        expected.attack_mask(!self.turn) & expected.col_king_mask(self.turn) == 0
+    }
+
+    /// whether `next` has the castling rights, en passant square and clocks
+    /// the generator gives after the piece `nibble` moves `from` `to`
+    fn is_successor_state(&self, from: u8, to: u8, nibble: u8, next: &BitBoardGame) -> bool {
+        let pawn_move = nibble & 0b111 == 0b100;
+        let rights = match self.castle_files {
+            Some((qs_file, ks_file)) => {
+                let [white_king, black_king] = [true, false]
+                    .map(|white| self.board.col_king_mask(white).trailing_zeros() as u8);
+                let kept = |right: bool, king: u8, rook: u8| {
+                    right && from != king && from != rook && to != rook
+                };
+                (
+                    kept(self.white_qs, white_king, qs_file),
+                    kept(self.white_ks, white_king, ks_file),
+                    kept(self.black_qs, black_king, 56 + qs_file),
+                    kept(self.black_ks, black_king, 56 + ks_file),
+                )
+            }
+            // a king move gives up both of its side's rights
+            None if nibble & 0b111 == 0b111 => (
+                self.white_qs && !self.turn,
+                self.white_ks && !self.turn,
+                self.black_qs && self.turn,
+                self.black_ks && self.turn,
+            ),
+            None => {
+                let kept = |right: bool, rook: u8| right && from != rook && to != rook;
+                (
+                    kept(self.white_qs, 7),
+                    kept(self.white_ks, 0),
+                    kept(self.black_qs, 63),
+                    kept(self.black_ks, 56),
+                )
+            }
+        };
+        let ep = (pawn_move && from.abs_diff(to) == 16).then_some((from + to) / 2);
+        let reset = pawn_move || self.board.piece_mask() & (1 << to) != 0;
+        let halfmove = if reset { 0 } else { self.halfmove + 1 };
+        let fullmove = self.fullmove + u16::from(!self.turn);
+        next.castling_rights() == rights
+            && next.ep == ep
+            && (next.halfmove, next.fullmove) == (halfmove, fullmove)
+            && next.castle_files == self.castle_files
+    }
+
+    /// The principal variation stored in `table`, following best moves from
+    /// this position for up to `max_len` plies until a position has no entry,
+    /// its move isn't legal or a position repeats
+    pub fn extract_pv(&self, table: &TTable, max_len: usize) -> Vec<u16> {
+        let mut pv = Vec::new();
+        let mut seen = HashSet::new();
+        let mut game = self.clone();
+        while pv.len() < max_len {
+            let hash = game.polyglot_hash();
+            if !seen.insert(hash) {
+                break;
+            }
+            let Some(entry) = table.get(hash) else {
+                break;
+            };
+            let Some(mov) = game.moves().into_iter().find(|m| m.mov == entry.best_move) else {
+                break;
+            };
+            pv.push(mov.mov);
+            game = mov.bbg;
+        }
+        pv
+    }
+
+    /// A legal move picked uniformly at random, None once the game is over
+    pub fn random_move(&self, rng: &mut impl RngCore) -> Option<BitBoardGameMove> {
+        let mut moves = self.moves();
+        if moves.is_empty() {
+            return None;
+        }
+        Some(moves.swap_remove(rng.gen_range(0..moves.len())))
+    }
 
+    /// Zobrist key of the position as used by Polyglot opening books, the
+    /// xor of the `POLYGLOT_RANDOM` keys for each piece (`64 * kind + 8 *
+    /// rank + file`), each castling right, the en passant file when a pawn
+    /// can actually take en passant, and white to move. Equal positions
+    /// hash equally whatever their move clocks
+    pub fn polyglot_hash(&self) -> u64 {
+        let mut hash = 0;
+        for white in [false, true] {
+            for (piece, mut pieces) in self.board.piece_type_masks(white).into_iter().enumerate() {
+                let kind = piece * 2 + white as usize;
+                while pieces != 0 {
+                    let (file, rank) = square_coords(pieces.trailing_zeros() as u8);
+                    hash ^= POLYGLOT_RANDOM[64 * kind + 8 * rank as usize + file as usize];
+                    pieces &= pieces - 1;
+                }
+            }
+        }
+        let (white_qs, white_ks, black_qs, black_ks) = self.castling_rights();
+        for (right, offset) in [(white_ks, 0), (white_qs, 1), (black_ks, 2), (black_qs, 3)] {
+            if right {
+                hash ^= POLYGLOT_RANDOM[768 + offset];
+            }
+        }
+        if let Some(ep) = self.capturable_ep() {
+            hash ^= POLYGLOT_RANDOM[772 + square_coords(ep).0 as usize];
+        }
+        if self.turn {
+            hash ^= POLYGLOT_RANDOM[780];
+        }
+        hash
+    }
+
+    /// Parse a FEN as a Chess960 position, `KQkq` castling rights select the
+    /// outermost rook on that side
+    pub fn from_fen_960(fen: &str) -> Option<Self> {
+        Self::parse_fen(fen, true)
+    }
+
+    /// `from_fen` that also rejects positions where the side that isn't to
+    /// move is in check, which no legal move can leave behind
+    pub fn from_fen_strict(fen: &str) -> Option<Self> {
+        Self::from_fen(fen).filter(Self::is_legal_position)
+    }
+
+    /// Chess960 start position `id` (0 to 959) in Scharnagl's numbering,
+    /// 518 being the standard start position. None for a larger id
+    pub fn from_960_id(id: u16) -> Option<Self> {
+        if id >= 960 {
+            return None;
+        }
+        let mut rank = [' '; 8];
+        let mut n = id as usize;
+        // light squared bishop on b, d, f or h then dark squared on a, c, e, g
+        rank[n % 4 * 2 + 1] = 'b';
+        n /= 4;
+        rank[n % 4 * 2] = 'b';
+        n /= 4;
+        // the rest are placed on the nth empty square left
+        let mut place = |nth: usize, piece: char| {
+            let file = (0..8).filter(|&f| rank[f] == ' ').nth(nth).unwrap();
+            rank[file] = piece;
+        };
+        place(n % 6, 'q');
+        n /= 6;
+        let (first, second) = [
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (1, 1),
+            (1, 2),
+            (1, 3),
+            (2, 2),
+            (2, 3),
+            (3, 3),
+        ][n];
+        place(first, 'n');
+        place(second, 'n');
+        for piece in ['r', 'k', 'r'] {
+            place(0, piece);
+        }
+        let black: String = rank.iter().collect();
+        let white = black.to_ascii_uppercase();
+        Self::from_fen_960(&format!(
+            "{black}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1"
+        ))
+    }
+
+    /// FEN string describing this position
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        let mut empty = 0;
         for i in 0..64 {
-            let mask = 1 << 63 - i;
-            let is_white = self.board[3] & mask != 0;
-
-            let c = match (
-                self.board[2] & mask != 0,
-                self.board[1] & mask != 0,
-                self.board[0] & mask != 0,
-            ) {
-                (false, false, false) => {
-                    if is_white {
-                        '*'
-                    } else {
-                        '-'
-                    }
+            let square = 63 - i;
+            let piece = match self.board.nibble(square) & 0b111 {
+                0b100 => 'p',
+                0b101 => 'n',
+                0b001 => 'b',
+                0b010 => 'r',
+                0b011 => 'q',
+                0b111 => 'k',
+                _ => ' ',
+            };
+            if piece == ' ' {
+                empty += 1;
+            } else {
+                if empty != 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                if self.board.nibble(square) & 0b1000 != 0 {
+                    fen.push(piece.to_ascii_uppercase());
+                } else {
+                    fen.push(piece);
+                }
+            }
+            if i % 8 == 7 {
+                if empty != 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                if i != 63 {
+                    fen.push('/');
                 }
-                (true, false, false) => 'p',
-                (true, false, true) => 'n',
-                (false, false, true) => 'b',
-                (false, true, false) => 'r',
-                (false, true, true) => 'q',
-                (true, true, true) => 'k',
-                _ => '#',
+            }
+        }
+
+        fen.push_str(if self.turn { " w " } else { " b " });
+
+        let castle_len = fen.len();
+        // Chess960 rights are written as rook files (Shredder-FEN)
+        let (qs, ks) = match self.castle_files {
+            Some((qs, ks)) => ((b'H' - qs) as char, (b'H' - ks) as char),
+            None => ('Q', 'K'),
+        };
+        for (right, c) in [
+            (self.white_ks, ks),
+            (self.white_qs, qs),
+            (self.black_ks, ks.to_ascii_lowercase()),
+            (self.black_qs, qs.to_ascii_lowercase()),
+        ] {
+            if right {
+                fen.push(c);
+            }
+        }
+        if fen.len() == castle_len {
+            fen.push('-');
+        }
+
+        match self.ep {
+            Some(sq) => {
+                let (file, rank) = square_coords(sq);
+                fen.push(' ');
+                fen.push((b'a' + file) as char);
+                fen.push((b'1' + rank) as char);
+            }
+            None => fen.push_str(" -"),
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove, self.fullmove));
+        fen
+    }
+
+    /// true if the side that just moved hasn't left its king attacked
+    pub fn is_legal_position(&self) -> bool {
+        self.board.attack_mask(self.turn) & self.board.col_king_mask(!self.turn) == 0
+    }
+
+    /// perft that checks every generated move leaves the mover's king safe,
+    /// reporting the position and move of the first one that doesn't
+    pub fn perft_strict(&self, limit: usize) -> Result<usize, IllegalMove> {
+        self.perft_strict_with(limit, &BitBoardGame::moves)
+    }
+
+    fn perft_strict_with<Gen: Fn(&BitBoardGame) -> Vec<BitBoardGameMove>>(
+        &self,
+        limit: usize,
+        gen: &Gen,
+    ) -> Result<usize, IllegalMove> {
+        if limit == 0 {
+            return Ok(1);
+        }
+        let mut total = 0;
+        for mov in gen(self) {
+            if !mov.bbg.is_legal_position() {
+                return Err(IllegalMove {
+                    fen: self.to_fen(),
+                    uci: mov.to_uci(),
+                });
+            }
+            total += mov.bbg.perft_strict_with(limit - 1, gen)?;
+        }
+        Ok(total)
+    }
+
+    /// squares the side to move (gained, lost) attacks on by playing `mov`
+    pub fn attack_delta(&self, mov: &BitBoardGameMove) -> (u64, u64) {
+        let before = self.board.attack_mask(self.turn);
+        let after = mov.bbg.board.attack_mask(self.turn);
+        (after & !before, before & !after)
+    }
+
+    /// legal moves split into (checks, captures, quiets)
+    /// a capture that gives check is only listed with the checks
+    pub fn moves_grouped(
+        &self,
+    ) -> (
+        Vec<BitBoardGameMove>,
+        Vec<BitBoardGameMove>,
+        Vec<BitBoardGameMove>,
+    ) {
+        let enemies = self.board.col_piece_mask(!self.turn);
+        let mut checks = Vec::new();
+        let mut captures = Vec::new();
+        let mut quiets = Vec::new();
+        for mov in self.moves() {
+            if mov.bbg.board.check_mask(mov.bbg.turn) != u64::MAX {
+                checks.push(mov);
+            } else if mov.bbg.board.col_piece_mask(!self.turn) != enemies {
+                captures.push(mov);
+            } else {
+                quiets.push(mov);
+            }
+        }
+        (checks, captures, quiets)
+    }
+
+    /// legal captures landing on `square`, en passant onto it included
+    pub fn captures_to(&self, square: u8) -> Vec<BitBoardGameMove> {
+        let enemies = self.board.col_piece_mask(!self.turn);
+        self.moves()
+            .into_iter()
+            .filter(|mov| {
+                ((mov.mov >> 6) & 63) as u8 == square
+                    && mov.bbg.board.col_piece_mask(!self.turn) != enemies
+            })
+            .collect()
+    }
+
+    /// Legal moves of the side to move's pieces of type `piece`, castles
+    /// counting as king moves and promotions as pawn moves. The full move
+    /// list is filtered by start square as the `gen_*` generators bake the
+    /// castling rights into the positions they make and the queen moves
+    /// come out of both the diagonal and orthogonal ones
+    pub fn moves_for_piece(&self, piece: Piece) -> Vec<BitBoardGameMove> {
+        let from = self.board.piece_type_masks(self.turn)[piece as usize];
+        self.moves()
+            .into_iter()
+            .filter(|mov| from & (1 << (mov.mov & 63)) != 0)
+            .collect()
+    }
+
+    /// number of each piece type, indexed `[colour][piece]` with white first
+    /// and pieces in `Piece` order
+    pub fn piece_counts(&self) -> [[u8; 6]; 2] {
+        [true, false].map(|white| {
+            self.board
+                .piece_type_masks(white)
+                .map(|pieces| pieces.count_ones() as u8)
+        })
+    }
+
+    /// material of one side using the standard 1/3/3/5/9 piece values
+    pub fn material(&self, white: bool) -> i32 {
+        self.material_weighted(white, &PIECE_VALUES)
+    }
+
+    /// White's `material` less black's, kept up to date by the move
+    /// generator and `do_move` instead of counting the pieces. Editing
+    /// `board` directly leaves it stale
+    pub fn incremental_material(&self) -> i32 {
+        self.material as i32
+    }
+
+    /// material the side to move wins by playing `mov`, the piece taken plus
+    /// what a promotion adds to the pawn
+    fn material_gain(&self, mov: &BitBoardGameMove) -> i16 {
+        if mov.castle {
+            return 0;
+        }
+        let from = (mov.mov & 63) as u8;
+        let to = ((mov.mov >> 6) & 63) as u8;
+        let pawn = PIECE_VALUES[Piece::Pawn as usize];
+        let mut gain = if self.ep == Some(to) && self.board.pawn_mask() & (1 << from) != 0 {
+            pawn
+        } else {
+            square_value(&self.board, to)
+        };
+        if mov.mov & (1 << 14) != 0 {
+            let promo = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+                [((mov.mov >> 12) & 3) as usize];
+            gain += PIECE_VALUES[promo as usize] - pawn;
+        }
+        gain as i16
+    }
+
+    /// material of one side with `weights` given in `Piece` order
+    pub fn material_weighted(&self, white: bool, weights: &[i32; 6]) -> i32 {
+        self.piece_counts()[!white as usize]
+            .iter()
+            .zip(weights)
+            .map(|(&count, weight)| count as i32 * weight)
+            .sum()
+    }
+
+    /// `mov` in algebraic form, naming the from file and/or rank only when
+    /// another legal move of the same piece type reaches the same square
+    pub fn algebraic(&self, mov: &BitBoardGameMove) -> AlgebraicMove {
+        let from = (mov.mov & 63) as u8;
+        let to = ((mov.mov >> 6) & 63) as u8;
+        let piece = nibble_piece(self.board.nibble(from));
+        let onto_own = self.board.col_piece_mask(self.turn) & (1 << to) != 0;
+        if piece == Piece::King && (from.abs_diff(to) == 2 || onto_own) {
+            // square indices count from the h file, Chess960 castles are
+            // encoded as the king taking its own rook
+            return if to < from {
+                AlgebraicMove::KSCastle
+            } else {
+                AlgebraicMove::QSCastle
             };
+        }
+        let (file, rank) = square_coords(from);
+        let (to_file, to_rank) = square_coords(to);
+        let target = AlgebraicPosition::Square(to_rank, to_file);
+        let origin = if piece == Piece::Pawn {
+            if from & 7 == to & 7 {
+                AlgebraicPosition::Piece(piece)
+            } else {
+                AlgebraicPosition::FilePiece(file, piece)
+            }
+        } else {
+            let rivals: Vec<u8> = self
+                .moves()
+                .iter()
+                .filter(|other| ((other.mov >> 6) & 63) as u8 == to)
+                .map(|other| (other.mov & 63) as u8)
+                .filter(|&other_from| {
+                    other_from != from && nibble_piece(self.board.nibble(other_from)) == piece
+                })
+                .collect();
+            if rivals.is_empty() {
+                AlgebraicPosition::Piece(piece)
+            } else if rivals.iter().all(|r| r & 7 != from & 7) {
+                AlgebraicPosition::FilePiece(file, piece)
+            } else if rivals.iter().all(|r| r >> 3 != rank) {
+                AlgebraicPosition::RankPiece(rank, piece)
+            } else {
+                AlgebraicPosition::SquarePiece(rank, file, piece)
+            }
+        };
+        if mov.mov & (1 << 14) != 0 {
+            let promo = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+                [((mov.mov >> 12) & 3) as usize];
+            AlgebraicMove::Promotion(origin, target, promo)
+        } else {
+            AlgebraicMove::Move(origin, target)
+        }
+    }
+
+    /// `mov` in standard algebraic notation including capture, check and
+    /// mate markers
+    pub fn san(&self, mov: &BitBoardGameMove) -> String {
+        let capture =
+            mov.bbg.board.col_piece_mask(!self.turn) != self.board.col_piece_mask(!self.turn);
+        let check = mov.bbg.board.check_mask(mov.bbg.turn) != u64::MAX;
+        let mate = check && mov.bbg.moves().is_empty();
+        self.algebraic(mov).to_san_string(capture, check, mate)
+    }
 
-            bstr.push(if is_white { c.to_ascii_uppercase() } else { c });
+    /// every legal move's UCI string mapped to its `san`, for move lists
+    pub fn legal_san_map(&self) -> HashMap<String, String> {
+        self.moves()
+            .iter()
+            .map(|mov| (mov.to_uci(), self.san(mov)))
+            .collect()
+    }
+}
+
+/// The legal move in `game` written as `san`, None if `san` doesn't parse,
+/// isn't legal or is ambiguous. Check and mate markers and a trailing `e.p.`
+/// are accepted but not verified
+#[cfg(feature = "std")]
+pub fn parse_san(game: &BitBoardGame, san: &str) -> Option<BitBoardGameMove> {
+    let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    let san = san.strip_suffix("e.p.").unwrap_or(san);
+    let alg = str_to_algebraic(san).ok()?;
+    game.move_from_algebraic(&alg)
+}
+
+#[cfg(feature = "std")]
+pub fn print_bitmask(mask: u64) {
+    let mut bstr = String::from("");
+    for i in 0..64 {
+        if i % 8 == 0 {
+            bstr.push('\n')
+        }
+        bstr.push(if (mask >> 63 - i) & 1 == 0 { '-' } else { 'X' });
+    }
+    println!("{:b}", mask);
+    println!("{}", bstr);
+}
+
+/// how `BitBoard::render` draws the pieces
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStyle {
+    /// FEN letters, `.` for empty squares
+    Ascii,
+    /// chess symbols, `.` for empty squares
+    Unicode,
+    /// `Ascii` with rank numbers on the left and files along the bottom
+    WithCoords,
+}
+
+#[cfg(feature = "std")]
+impl BitBoard {
+    /// the `Display` board with every square attacked by `turn` in brackets
+    pub fn attack_string(&self, turn: bool) -> String {
+        let attacks = self.attack_mask(turn);
+        let mut bstr = String::from("");
+        let squares = self.to_string();
+        for (i, c) in squares.chars().filter(|c| *c != '\n').enumerate() {
+            if (attacks >> (63 - i)) & 1 == 0 {
+                bstr.push(' ');
+                bstr.push(c);
+                bstr.push(' ');
+            } else {
+                bstr.push('[');
+                bstr.push(c);
+                bstr.push(']');
+            }
+            if i % 8 == 7 {
+                bstr.push('\n');
+            }
+        }
+        bstr
+    }
+
+    /// print the squares attacked by `turn` over the board
+    pub fn debug_attacks(&self, turn: bool) {
+        println!("{}", self.attack_string(turn));
+    }
+
+    /// The board from rank 8 down to rank 1, one line per rank
+    pub fn render(&self, style: RenderStyle) -> String {
+        let mut out = String::new();
+        for rank in (0..8).rev() {
+            if style == RenderStyle::WithCoords {
+                out.push((b'1' + rank) as char);
+                out.push(' ');
+            }
+            for file in 0..8 {
+                let nibble = self.nibble(square_index(file, rank));
+                if nibble & 0b111 == 0 {
+                    out.push('.');
+                    continue;
+                }
+                let colour = if nibble & 0b1000 != 0 {
+                    PlayerColour::White
+                } else {
+                    PlayerColour::Black
+                };
+                let piece = ColouredPiece::from_parts(colour, nibble_piece(nibble));
+                match style {
+                    RenderStyle::Unicode => out.push(unicode_symbol(piece)),
+                    _ => out.push_str(&piece.to_string()),
+                }
+            }
+            out.push('\n');
+        }
+        if style == RenderStyle::WithCoords {
+            out.push_str("  abcdefgh\n");
+        }
+        out
+    }
+}
+
+/// chess symbol of a piece
+#[cfg(feature = "std")]
+const fn unicode_symbol(piece: ColouredPiece) -> char {
+    match piece {
+        ColouredPiece::WhiteKing => '\u{2654}',
+        ColouredPiece::WhiteQueen => '\u{2655}',
+        ColouredPiece::WhiteRook => '\u{2656}',
+        ColouredPiece::WhiteBishop => '\u{2657}',
+        ColouredPiece::WhiteKnight => '\u{2658}',
+        ColouredPiece::WhitePawn => '\u{2659}',
+        ColouredPiece::BlackKing => '\u{265A}',
+        ColouredPiece::BlackQueen => '\u{265B}',
+        ColouredPiece::BlackRook => '\u{265C}',
+        ColouredPiece::BlackBishop => '\u{265D}',
+        ColouredPiece::BlackKnight => '\u{265E}',
+        ColouredPiece::BlackPawn => '\u{265F}',
+    }
+}
+
+#[cfg(feature = "std")]
+impl BitBoard {
+    /// `Display` character of `square`, `*` for an empty square with the
+    /// white bit set and `#` for an invalid piece code
+    fn display_char(&self, square: u8) -> char {
+        let mask = 1 << square;
+        let is_white = self.board[3] & mask != 0;
+
+        let c = match (
+            self.board[2] & mask != 0,
+            self.board[1] & mask != 0,
+            self.board[0] & mask != 0,
+        ) {
+            (false, false, false) => {
+                if is_white {
+                    '*'
+                } else {
+                    '-'
+                }
+            }
+            (true, false, false) => 'p',
+            (true, false, true) => 'n',
+            (false, false, true) => 'b',
+            (false, true, false) => 'r',
+            (false, true, true) => 'q',
+            (true, true, true) => 'k',
+            _ => '#',
+        };
+
+        if is_white {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+
+    /// The `Display` board as seen by `perspective`, Black's view has rank 1
+    /// at the top and the h-file on the left
+    pub fn render_from(&self, perspective: PlayerColour) -> String {
+        let mut bstr = String::from("");
+        for i in 0..64 {
+            let square = match perspective {
+                PlayerColour::White => 63 - i,
+                PlayerColour::Black => i,
+            };
+            bstr.push(self.display_char(square));
             if i % 8 == 7 {
                 bstr.push('\n');
             }
         }
-        f.write_fmt(format_args!("{}", bstr))
+        bstr
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for BitBoard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render_from(PlayerColour::White))
     }
 }
 
+#[cfg(feature = "std")]
 impl Display for BitBoardGame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.board))
     }
 }
 
+#[cfg(feature = "std")]
 impl Display for BitBoardGameMove {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.bbg))
     }
 }
 
-impl Move for BitBoardGameMove {
-    fn to_uci(&self) -> String {
-        let ox = ('h' as u8 - (self.mov & 7) as u8) as char;
-        let oy = ('1' as u8 + ((self.mov >> 3) & 7) as u8) as char;
-        let nx = ('h' as u8 - ((self.mov >> 6) & 7) as u8) as char;
-        let ny = ('1' as u8 + ((self.mov >> 9) & 7) as u8) as char;
-        format!("{ox}{oy}{nx}{ny}")
+#[cfg(feature = "std")]
+impl BitBoardGameMove {
+    /// The type of the piece making the move, `King` for a castle and `Pawn`
+    /// for a promotion
+    pub fn moving_piece(&self) -> Piece {
+        if self.castle {
+            Piece::King
+        } else if self.mov & (1 << 14) != 0 {
+            Piece::Pawn
+        } else {
+            nibble_piece(self.bbg.board.nibble(((self.mov >> 6) & 63) as u8))
+        }
+    }
+
+    /// The piece a pawn promotes to, None if this isn't a promotion
+    pub fn promotion(&self) -> Option<Piece> {
+        (self.mov & (1 << 14) != 0).then(|| {
+            [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+                [((self.mov >> 12) & 3) as usize]
+        })
+    }
+
+    /// This move, played from `game`, in long algebraic notation such as
+    /// `Ng1-f3`, `Bb5xc6` or `e7-e8=Q`, castles are written `O-O`/`O-O-O`
+    pub fn to_lan(&self, game: &BitBoardGame) -> String {
+        if self.castle {
+            return game.algebraic(self).to_string();
+        }
+        let uci = self.mov.to_uci();
+        let capture =
+            self.bbg.board.col_piece_mask(!game.turn) != game.board.col_piece_mask(!game.turn);
+        let mut lan = format!(
+            "{}{}{}{}",
+            piece_letter(self.moving_piece()),
+            &uci[..2],
+            if capture { 'x' } else { '-' },
+            &uci[2..4]
+        );
+        if let Some(promo) = self.promotion() {
+            lan.push('=');
+            lan.push_str(piece_letter(promo));
+        }
+        lan
+    }
+}
+
+#[cfg(feature = "std")]
+impl Move for BitBoardGameMove {
+    fn to_uci(&self) -> String {
+        self.mov.to_uci()
+    }
+}
+
+#[cfg(feature = "std")]
+impl BitBoardGame {
+    fn from_parts(
+        board: BitBoard,
+        turn: bool,
+        white_qs: bool,
+        white_ks: bool,
+        black_qs: bool,
+        black_ks: bool,
+        ep: Option<u8>,
+    ) -> Self {
+        Self {
+            board,
+            turn,
+            white_qs,
+            white_ks,
+            black_qs,
+            black_ks,
+            ep,
+            halfmove: 0,
+            fullmove: 1,
+            castle_files: None,
+            material: 0,
+        }
+    }
+    /// shared FEN parser, castling rights given as rook files (e.g. `HAha`)
+    /// always select Chess960 castling
+    fn parse_fen(fen: &str, chess960: bool) -> Option<Self> {
+        let mut fen_parts = fen.trim().split(" ");
+        let fenboard = fen_parts.next()?;
+        let turn = match fen_parts.next()? {
+            "w" => true,
+            "b" => false,
+            _ => return None,
+        };
+
+        let castle_rights = fen_parts.next()?;
+        // X-FEN and Shredder-FEN name the castling rooks by file
+        let rook_files = castle_rights
+            .chars()
+            .any(|c| matches!(c.to_ascii_uppercase(), 'A'..='H'));
+        let standard = !chess960;
+        let chess960 = chess960 || rook_files;
+        let white_ks_castle = !chess960 && castle_rights.contains('K');
+        let white_qs_castle = !chess960 && castle_rights.contains('Q');
+        let black_ks_castle = !chess960 && castle_rights.contains('k');
+        let black_qs_castle = !chess960 && castle_rights.contains('q');
+
+        // the square behind a pawn that just moved two, on the 6th rank when
+        // white is to move and the 3rd when black is
+        // the en passant square and move clocks are often left off
+        let enpassant = match fen_parts.next().unwrap_or("-").as_bytes() {
+            b"-" => None,
+            &[file @ b'a'..=b'h', rank] if rank == if turn { b'6' } else { b'3' } => {
+                Some(square_index(file - b'a', rank - b'1'))
+            }
+            _ => return None,
+        };
+
+        let halfmove = match fen_parts.next() {
+            Some(clock) => clock.parse().ok()?,
+            None => 0,
+        };
+        let fullmove = match fen_parts.next() {
+            Some(clock) => clock.parse().ok()?,
+            None => 1,
+        };
+
+        let mut board: [u64; 4] = [0; 4];
+        let mut counter = 0;
+        for c in fenboard.replace('/', "").chars() {
+            if let Some(skip) = c.to_digit(10) {
+                counter += skip;
+                continue;
+            }
+
+            if counter >= 64 {
+                return None;
+            }
+            let mut piece_idx = match c.to_ascii_uppercase() {
+                'P' => 0b100,
+                'N' => 0b101,
+                'B' => 0b001,
+                'R' => 0b010,
+                'Q' => 0b011,
+                'K' => 0b111,
+                _ => return None,
+            };
+            piece_idx |= if c.is_ascii_uppercase() { 0b1000 } else { 0 };
+            // FEN runs from a8 along each rank then down the board
+            let square = square_index((counter % 8) as u8, 7 - (counter / 8) as u8);
+            board
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, v)| *v |= ((piece_idx >> i) & 1) << square);
+            counter += 1;
+        }
+
+        let board = BitBoard { board };
+        // move generation assumes a single king per side and no pawns on the
+        // back ranks
+        if counter == 64
+            && board.col_king_mask(true).count_ones() == 1
+            && board.col_king_mask(false).count_ones() == 1
+            && board.pawn_mask() & (TOP_MASK | BOT_MASK) == 0
+            && enpassant.is_none_or(|ep| {
+                let pawn = if turn { ep - 8 } else { ep + 8 };
+                board.col_pawn_mask(!turn) & (1 << pawn) != 0
+            })
+        {
+            let mut game = BitBoardGame {
+                board,
+                turn,
+                white_qs: white_qs_castle,
+                white_ks: white_ks_castle,
+                black_qs: black_qs_castle,
+                black_ks: black_ks_castle,
+                ep: enpassant,
+                halfmove,
+                fullmove,
+                castle_files: None,
+                material: 0,
+            };
+            game.material = (game.material(true) - game.material(false)) as i16;
+            if chess960 {
+                game.set_960_rights(castle_rights)?;
+                // file letters for the corner rooks of a king on the e-file
+                // are just standard castling
+                if standard && game.is_standard_castling() {
+                    game.castle_files = None;
+                }
+            }
+            Some(game)
+        } else {
+            None
+        }
+    }
+
+    /// true if the castling rooks are on the a and h files and every side
+    /// that may castle has its king on the e-file
+    fn is_standard_castling(&self) -> bool {
+        let king_on_e = |white: bool, base: u8| {
+            self.board.col_king_mask(white) == 1 << (base + square_index(4, 0))
+        };
+        self.castle_files == Some((square_index(0, 0), square_index(7, 0)))
+            && (!(self.white_qs || self.white_ks) || king_on_e(true, 0))
+            && (!(self.black_qs || self.black_ks) || king_on_e(false, 56))
+    }
+
+    /// Read a Chess960 castling field, either `KQkq` meaning the outermost
+    /// rook on each side or the files of the castling rooks
+    fn set_960_rights(&mut self, rights: &str) -> Option<()> {
+        let mut files = [None, None];
+        for c in rights.chars().filter(|&c| c != '-') {
+            let white = c.is_ascii_uppercase();
+            let base = if white { 0 } else { 56 };
+            let king = self.board.col_king_mask(white).trailing_zeros() as u8;
+            if !(base..base + 8).contains(&king) {
+                return None;
+            }
+            let rook_piece = 0b010 | if white { 0b1000 } else { 0 };
+            let is_rook = |x: u8| self.board.nibble(base + x) == rook_piece;
+            let king = king - base;
+            let rook = match c.to_ascii_uppercase() {
+                'K' => (0..king).find(|&x| is_rook(x))?,
+                'Q' => (king + 1..8).rev().find(|&x| is_rook(x))?,
+                file @ 'A'..='H' => 7 - (file as u8 - b'A'),
+                _ => return None,
+            };
+            if !is_rook(rook) || rook == king {
+                return None;
+            }
+            let kingside = rook < king;
+            match files[kingside as usize] {
+                Some(file) if file != rook => return None,
+                _ => files[kingside as usize] = Some(rook),
+            }
+            match (white, kingside) {
+                (true, false) => self.white_qs = true,
+                (true, true) => self.white_ks = true,
+                (false, false) => self.black_qs = true,
+                (false, true) => self.black_ks = true,
+            }
+        }
+        self.castle_files = Some((files[0].unwrap_or(7), files[1].unwrap_or(0)));
+        Some(())
+    }
+
+    /// every legal move pushed onto `next`, each successor carrying its
+    /// incremental material
+    fn gen_into<B: MoveBuffer>(&self, next: &mut B) {
+        let start = next.as_mut_slice().len();
+        let mut genny = GenericMoveGenerator {
+            next,
+            halfmove: self.halfmove,
+            fullmove: self.fullmove,
+        };
+        match self.castle_files {
+            None => self.proc_movs(&mut genny),
+            Some(files) => self.gen_960_moves(files, &mut genny),
+        }
+        for mov in next.as_mut_slice()[start..].iter_mut() {
+            let gain = self.material_gain(mov);
+            mov.bbg.material = if self.turn {
+                self.material + gain
+            } else {
+                self.material - gain
+            };
+        }
+    }
+
+    /// Chess960 moves, everything but castling is generated as if no side
+    /// could castle then the rights are restored from the rook start squares
+    fn gen_960_moves<B: MoveBuffer>(
+        &self,
+        (qs_file, ks_file): (u8, u8),
+        genny: &mut GenericMoveGenerator<B>,
+    ) {
+        let start = genny.next.as_mut_slice().len();
+        self.board
+            .gen_moves::<false, false, false, false, _>(self.turn, genny, self.ep);
+        let white_king = self.board.col_king_mask(true).trailing_zeros() as u16;
+        let black_king = self.board.col_king_mask(false).trailing_zeros() as u16;
+        for next in genny.next.as_mut_slice()[start..].iter_mut() {
+            let from = next.mov & 63;
+            let to = (next.mov >> 6) & 63;
+            let kept = |right: bool, king: u16, rook: u8| {
+                right && from != king && from != rook as u16 && to != rook as u16
+            };
+            next.bbg.white_qs = kept(self.white_qs, white_king, qs_file);
+            next.bbg.white_ks = kept(self.white_ks, white_king, ks_file);
+            next.bbg.black_qs = kept(self.black_qs, black_king, 56 + qs_file);
+            next.bbg.black_ks = kept(self.black_ks, black_king, 56 + ks_file);
+            next.bbg.castle_files = self.castle_files;
+        }
+        let (qs, ks) = if self.turn {
+            (self.white_qs, self.white_ks)
+        } else {
+            (self.black_qs, self.black_ks)
+        };
+        // the king ends on the c or g file and the rook on the d or f file
+        if qs {
+            self.gen_960_castle(qs_file, 5, 4, genny);
+        }
+        if ks {
+            self.gen_960_castle(ks_file, 1, 2, genny);
+        }
+    }
+
+    fn gen_960_castle<B: MoveBuffer>(
+        &self,
+        rook_file: u8,
+        king_file: u8,
+        rook_to_file: u8,
+        genny: &mut GenericMoveGenerator<B>,
+    ) {
+        let turn = self.turn;
+        let base = if turn { 0 } else { 56 };
+        let king = self.board.col_king_mask(turn).trailing_zeros() as u8;
+        let rook = base + rook_file;
+        let (king_to, rook_to) = (base + king_file, base + rook_to_file);
+        let span = |a: u8, b: u8| (u64::MAX >> (63 - a.max(b))) & (u64::MAX << a.min(b));
+        let must_be_empty =
+            (span(king, king_to) | span(rook, rook_to)) & !(1 << king) & !(1 << rook);
+        let must_be_safe = span(king, king_to);
+        if self.board.piece_mask() & must_be_empty != 0
+            || self.board.attack_mask(!turn) & must_be_safe != 0
+        {
+            return;
+        }
+        let mut b = self.board.clone();
+        let (king_piece, rook_piece) = (b.nibble(king), b.nibble(rook));
+        b.clear(king);
+        b.clear(rook);
+        b.set(king_to, king_piece);
+        b.set(rook_to, rook_piece);
+        // the rook may have been shielding the king's destination
+        if b.attack_mask(!turn) & (1 << king_to) != 0 {
+            return;
+        }
+        let mut next_state = if turn {
+            BitBoardGame::from_parts(b, !turn, false, false, self.black_qs, self.black_ks, None)
+        } else {
+            BitBoardGame::from_parts(b, !turn, self.white_qs, self.white_ks, false, false, None)
+        };
+        next_state.castle_files = self.castle_files;
+        genny.tick(&mut next_state, turn, false);
+        // encoded as the king capturing its own rook, as in UCI_Chess960
+        genny.next.push(BitBoardGameMove {
+            mov: ((rook as u16) << 6) + king as u16,
+            bbg: next_state,
+            castle: true,
+        });
+    }
+}
+
+/// most legal moves known in any position is 218
+#[cfg(feature = "std")]
+pub const MAX_MOVES: usize = 256;
+
+/// placeholder filling the unused tail of a `MoveList`
+#[cfg(feature = "std")]
+const EMPTY_MOVE: BitBoardGameMove = BitBoardGameMove {
+    mov: 0,
+    bbg: BitBoardGame {
+        board: BitBoard { board: [0; 4] },
+        turn: true,
+        white_qs: false,
+        white_ks: false,
+        black_qs: false,
+        black_ks: false,
+        ep: None,
+        halfmove: 0,
+        fullmove: 1,
+        castle_files: None,
+        material: 0,
+    },
+    castle: false,
+};
+
+/// fixed capacity move buffer kept on the stack
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [BitBoardGameMove; MAX_MOVES],
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl MoveList {
+    pub fn new() -> Self {
+        Self {
+            moves: [EMPTY_MOVE; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, BitBoardGameMove> {
+        self.moves[..self.len].iter()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::ops::Deref for MoveList {
+    type Target = [BitBoardGameMove];
+
+    fn deref(&self) -> &Self::Target {
+        &self.moves[..self.len]
+    }
+}
+
+#[cfg(feature = "std")]
+impl IntoIterator for MoveList {
+    type Item = BitBoardGameMove;
+    type IntoIter = std::iter::Take<std::array::IntoIter<BitBoardGameMove, MAX_MOVES>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.into_iter().take(self.len)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a BitBoardGameMove;
+    type IntoIter = std::slice::Iter<'a, BitBoardGameMove>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// storage the move generator can write generated moves to
+#[cfg(feature = "std")]
+trait MoveBuffer {
+    fn push(&mut self, mov: BitBoardGameMove);
+    fn as_mut_slice(&mut self) -> &mut [BitBoardGameMove];
+}
+
+#[cfg(feature = "std")]
+impl MoveBuffer for Vec<BitBoardGameMove> {
+    fn push(&mut self, mov: BitBoardGameMove) {
+        Vec::push(self, mov)
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [BitBoardGameMove] {
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl MoveBuffer for MoveList {
+    fn push(&mut self, mov: BitBoardGameMove) {
+        self.moves[self.len] = mov;
+        self.len += 1;
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [BitBoardGameMove] {
+        &mut self.moves[..self.len]
     }
 }
 
-impl BitBoardGame {
-    fn from_parts(
-        board: BitBoard,
-        turn: bool,
-        white_qs: bool,
-        white_ks: bool,
-        black_qs: bool,
-        black_ks: bool,
-        ep: Option<u8>,
-    ) -> Self {
-        Self {
-            board,
-            turn,
-            white_qs,
-            white_ks,
-            black_qs,
-            black_ks,
-            ep,
-        }
-    }
+#[cfg(feature = "std")]
+struct GenericMoveGenerator<'a, B: MoveBuffer> {
+    next: &'a mut B,
+    halfmove: u16,
+    fullmove: u16,
 }
 
-struct GenericMoveGenerator {
-    next: Vec<BitBoardGameMove>,
+#[cfg(feature = "std")]
+impl<B: MoveBuffer> GenericMoveGenerator<'_, B> {
+    /// advance the move clocks of the position being generated from onto `next`
+    #[inline(always)]
+    fn tick(&self, next: &mut BitBoardGame, turn: bool, reset: bool) {
+        next.halfmove = if reset { 0 } else { self.halfmove + 1 };
+        next.fullmove = if turn {
+            self.fullmove
+        } else {
+            self.fullmove + 1
+        };
+    }
 }
 
-impl OnMove for GenericMoveGenerator {
+#[cfg(feature = "std")]
+impl<B: MoveBuffer> OnMove for GenericMoveGenerator<'_, B> {
     fn on_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
         &mut self,
         turn: bool,
@@ -1469,7 +3315,7 @@ impl OnMove for GenericMoveGenerator {
     ) {
         let mut b = me.clone();
         b.mov(from, to);
-        let next_state = BitBoardGame::from_parts(
+        let mut next_state = BitBoardGame::from_parts(
             b,
             !turn,
             from != 7 && to != 7 && WQ,
@@ -1478,10 +3324,16 @@ impl OnMove for GenericMoveGenerator {
             from != 56 && to != 56 && BK,
             None,
         );
+        self.tick(
+            &mut next_state,
+            turn,
+            me.piece_mask() & (1 << to) != 0 || me.pawn_mask() & (1 << from) != 0,
+        );
         let next_move = ((to as u16) << 6) + from as u16;
         let next_bbgm = BitBoardGameMove {
             mov: next_move,
             bbg: next_state,
+            castle: false,
         };
         self.next.push(next_bbgm);
     }
@@ -1495,7 +3347,7 @@ impl OnMove for GenericMoveGenerator {
     ) {
         let mut b = me.clone();
         b.mov(from, to);
-        let next_state = BitBoardGame::from_parts(
+        let mut next_state = BitBoardGame::from_parts(
             b,
             !turn,
             WQ && !turn,
@@ -1504,10 +3356,12 @@ impl OnMove for GenericMoveGenerator {
             BK && turn,
             None,
         );
+        self.tick(&mut next_state, turn, me.piece_mask() & (1 << to) != 0);
         let next_move = ((to as u16) << 6) + from as u16;
         let next_bbgm = BitBoardGameMove {
             mov: next_move,
             bbg: next_state,
+            castle: false,
         };
         self.next.push(next_bbgm);
     }
@@ -1526,11 +3380,13 @@ impl OnMove for GenericMoveGenerator {
         } else {
             b.clear(to + 8);
         }
-        let next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, BQ, BK, None);
+        let mut next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, BQ, BK, None);
+        self.tick(&mut next_state, turn, true);
         let next_move = ((to as u16) << 6) + from as u16;
         let next_bbgm = BitBoardGameMove {
             mov: next_move,
             bbg: next_state,
+            castle: false,
         };
         self.next.push(next_bbgm);
     }
@@ -1544,21 +3400,25 @@ impl OnMove for GenericMoveGenerator {
         if turn {
             b.mov(7, 4);
             b.mov(3, 5);
-            let next_state = BitBoardGame::from_parts(b, !turn, false, false, BQ, BK, None);
+            let mut next_state = BitBoardGame::from_parts(b, !turn, false, false, BQ, BK, None);
+            self.tick(&mut next_state, turn, false);
             let next_move = (5 << 6) + 3;
             let next_bbgm = BitBoardGameMove {
                 mov: next_move,
                 bbg: next_state,
+                castle: true,
             };
             self.next.push(next_bbgm);
         } else {
             b.mov(63, 60);
             b.mov(59, 61);
-            let next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, false, false, None);
+            let mut next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, false, false, None);
+            self.tick(&mut next_state, turn, false);
             let next_move = (61 << 6) + 59;
             let next_bbgm = BitBoardGameMove {
                 mov: next_move,
                 bbg: next_state,
+                castle: true,
             };
             self.next.push(next_bbgm);
         }
@@ -1573,21 +3433,25 @@ impl OnMove for GenericMoveGenerator {
         if turn {
             b.mov(0, 2);
             b.mov(3, 1);
-            let next_state = BitBoardGame::from_parts(b, !turn, false, false, BQ, BK, None);
+            let mut next_state = BitBoardGame::from_parts(b, !turn, false, false, BQ, BK, None);
+            self.tick(&mut next_state, turn, false);
             let next_move = (1 << 6) + 3;
             let next_bbgm = BitBoardGameMove {
                 mov: next_move,
                 bbg: next_state,
+                castle: true,
             };
             self.next.push(next_bbgm);
         } else {
             b.mov(56, 58);
             b.mov(59, 57);
-            let next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, false, false, None);
+            let mut next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, false, false, None);
+            self.tick(&mut next_state, turn, false);
             let next_move = (57 << 6) + 59;
             let next_bbgm = BitBoardGameMove {
                 mov: next_move,
                 bbg: next_state,
+                castle: true,
             };
             self.next.push(next_bbgm);
         }
@@ -1602,20 +3466,24 @@ impl OnMove for GenericMoveGenerator {
         let mut b = me.clone();
         if turn {
             b.mov(from, from + 16);
-            let next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, BQ, BK, Some(from + 8));
+            let mut next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, BQ, BK, Some(from + 8));
+            self.tick(&mut next_state, turn, true);
             let next_move = ((from as u16 + 16) << 6) + from as u16;
             let next_bbgm = BitBoardGameMove {
                 mov: next_move,
                 bbg: next_state,
+                castle: false,
             };
             self.next.push(next_bbgm);
         } else {
             b.mov(from, from - 16);
-            let next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, BQ, BK, Some(from - 8));
+            let mut next_state = BitBoardGame::from_parts(b, !turn, WQ, WK, BQ, BK, Some(from - 8));
+            self.tick(&mut next_state, turn, true);
             let next_move = ((from as u16 - 16) << 6) + from as u16;
             let next_bbgm = BitBoardGameMove {
                 mov: next_move,
                 bbg: next_state,
+                castle: false,
             };
             self.next.push(next_bbgm);
         }
@@ -1632,7 +3500,7 @@ impl OnMove for GenericMoveGenerator {
         let mut b = me.clone();
         b.clear(from);
         b.set(to, piece);
-        let next_state = BitBoardGame::from_parts(
+        let mut next_state = BitBoardGame::from_parts(
             b,
             !turn,
             to != 7 && WQ,
@@ -1641,11 +3509,1420 @@ impl OnMove for GenericMoveGenerator {
             to != 56 && BK,
             None,
         );
-        let next_move = ((to as u16) << 6) + from as u16;
+        self.tick(&mut next_state, turn, true);
+        let next_move = promotion_flags(piece) + ((to as u16) << 6) + from as u16;
         let next_bbgm = BitBoardGameMove {
             mov: next_move,
             bbg: next_state,
+            castle: false,
         };
         self.next.push(next_bbgm);
     }
 }
+
+/// packed promotion flag and type (see `BBMove`) for a promotion piece nibble
+#[cfg(feature = "std")]
+pub(crate) const fn promotion_flags(piece: u8) -> u16 {
+    let promo_type = match piece & 0b111 {
+        0b101 => 0b00,
+        0b001 => 0b01,
+        0b010 => 0b10,
+        _ => 0b11,
+    };
+    (1 << 14) | (promo_type << 12)
+}
+
+/// `PIECE_VALUES` value of the piece on `square`, 0 if it is empty
+#[cfg(feature = "std")]
+const fn square_value(board: &BitBoard, square: u8) -> i32 {
+    let nibble = board.nibble(square);
+    if nibble & 0b111 == 0 {
+        0
+    } else {
+        PIECE_VALUES[nibble_piece(nibble) as usize]
+    }
+}
+
+/// piece type of a non empty 4 bit piece code
+#[cfg(feature = "std")]
+const fn nibble_piece(nibble: u8) -> Piece {
+    match nibble & 0b111 {
+        0b001 => Piece::Bishop,
+        0b010 => Piece::Rook,
+        0b011 => Piece::Queen,
+        0b100 => Piece::Pawn,
+        0b101 => Piece::Knight,
+        _ => Piece::King,
+    }
+}
+
+/// 4 bit code of a piece
+fn coloured_nibble(piece: ColouredPiece) -> u8 {
+    let code = match piece.piece() {
+        Piece::Pawn => 0b100,
+        Piece::Knight => 0b101,
+        Piece::Bishop => 0b001,
+        Piece::Rook => 0b010,
+        Piece::Queen => 0b011,
+        Piece::King => 0b111,
+    };
+    if piece.is_white() {
+        code | 0b1000
+    } else {
+        code
+    }
+}
+
+/// exchange value in centipawns of the piece with 4 bit code `nibble`
+const fn see_value(nibble: u8) -> i32 {
+    match nibble & 0b111 {
+        0b100 => 100,
+        0b101 | 0b001 => 300,
+        0b010 => 500,
+        0b011 => 900,
+        0b111 => 20000,
+        _ => 0,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{
+        bit_board::{
+            parse_san, square_coords, square_index, BitBoard, BitBoardGame, BitBoardGameMove,
+            BoardError, GameStatus, IllegalMove, MoveList, RenderStyle,
+        },
+        game::{ChessGame, Move},
+        perft, perft_stats,
+        piece::{Piece, PlayerColour},
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use std::collections::HashSet;
+
+    const REFERENCE_FENS: [&str; 6] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ];
+
+    const D_FILE: u64 = 0x0101010101010101 << 4;
+
+    /// bit index of a square name like "e4"
+    fn sq(name: &str) -> u8 {
+        let name = name.as_bytes();
+        square_index(name[0] - b'a', name[1] - b'1')
+    }
+
+    #[test]
+    fn render_styles() {
+        let board = BitBoardGame::new().board;
+        assert_eq!(
+            board.render(RenderStyle::Ascii),
+            "rnbqkbnr\npppppppp\n........\n........\n........\n........\nPPPPPPPP\nRNBQKBNR\n"
+        );
+        assert_eq!(
+            board.render(RenderStyle::Unicode),
+            "♜♞♝♛♚♝♞♜\n♟♟♟♟♟♟♟♟\n........\n........\n........\n........\n♙♙♙♙♙♙♙♙\n♖♘♗♕♔♗♘♖\n"
+        );
+        assert_eq!(
+            board.render(RenderStyle::WithCoords),
+            "8 rnbqkbnr\n7 pppppppp\n6 ........\n5 ........\n4 ........\n3 ........\n2 PPPPPPPP\n1 RNBQKBNR\n  abcdefgh\n"
+        );
+    }
+
+    #[test]
+    fn perspective_rendering() {
+        let board = BitBoardGame::new().board;
+        let white = board.render_from(PlayerColour::White);
+        assert_eq!(white, board.to_string());
+        assert_eq!(
+            board.render_from(PlayerColour::Black),
+            "RNBKQBNR\nPPPPPPPP\n--------\n--------\n--------\n--------\npppppppp\nrnbkqbnr\n"
+        );
+        // black's view is white's turned upside down
+        let turned: String = white.trim_end().chars().rev().collect::<String>() + "\n";
+        assert_eq!(board.render_from(PlayerColour::Black), turned);
+    }
+
+    #[test]
+    fn moving_pieces() {
+        let gs = BitBoardGame::from_fen("4k3/1P6/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let piece = |gs: &BitBoardGame, uci| gs.move_from_uci(uci).unwrap().moving_piece();
+        assert_eq!(piece(&gs, "a1a5"), Piece::Rook);
+        assert_eq!(piece(&gs, "e1g1"), Piece::King);
+        assert_eq!(piece(&gs, "e1c1"), Piece::King);
+        assert_eq!(piece(&gs, "b7b8q"), Piece::Pawn);
+        assert_eq!(piece(&gs, "b7b8n"), Piece::Pawn);
+        assert_eq!(piece(&BitBoardGame::new(), "g1f3"), Piece::Knight);
+
+        // a Chess960 castle with the rook already on f1 is written like the
+        // rook move it leaves behind
+        let frc = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4KR2 w F - 0 1").unwrap();
+        assert_eq!(piece(&frc, "e1f1"), Piece::King);
+        let rook = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").unwrap();
+        assert_eq!(piece(&rook, "e1f1"), Piece::Rook);
+        assert!(
+            frc.move_from_uci("e1f1").unwrap().bbg.board
+                == rook.move_from_uci("e1f1").unwrap().bbg.board
+        );
+    }
+
+    #[test]
+    fn san_parsing() {
+        let uci = |gs: &BitBoardGame, san: &str| parse_san(gs, san).map(|mov| mov.to_uci());
+        let gs = BitBoardGame::from_fen(
+            "r1b2rk1/2q1bppp/p2p1n2/npp1p3/3PP3/2P2N1P/PPB2PP1/RNBQR1K1 w - - 1 12",
+        )
+        .unwrap();
+        assert_eq!(uci(&gs, "Nbd2").as_deref(), Some("b1d2"));
+        assert_eq!(uci(&gs, "Nfd2").as_deref(), Some("f3d2"));
+        assert_eq!(uci(&gs, "N1d2").as_deref(), Some("b1d2"));
+        assert_eq!(uci(&gs, "Nd2"), None);
+        assert_eq!(uci(&gs, "dxe5").as_deref(), Some("d4e5"));
+        assert_eq!(uci(&gs, "Nxe5").as_deref(), Some("f3e5"));
+        assert_eq!(uci(&gs, "d5").as_deref(), Some("d4d5"));
+        assert_eq!(uci(&gs, "Bg5+").as_deref(), Some("c1g5"));
+        assert_eq!(uci(&gs, "Qe2!?").as_deref(), Some("d1e2"));
+        assert_eq!(uci(&gs, "Rf1").as_deref(), Some("e1f1"));
+        assert_eq!(uci(&gs, "Be5"), None);
+        assert_eq!(uci(&gs, "O-O"), None);
+        assert_eq!(uci(&gs, "Zz9"), None);
+
+        let gs = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(uci(&gs, "exd6").as_deref(), Some("e5d6"));
+        assert_eq!(uci(&gs, "exd6 e.p.").as_deref(), Some("e5d6"));
+        assert_eq!(uci(&gs, "exd6e.p.+").as_deref(), Some("e5d6"));
+
+        let gs = BitBoardGame::from_fen("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(uci(&gs, "e8=Q").as_deref(), Some("e7e8q"));
+        assert_eq!(uci(&gs, "e8N").as_deref(), Some("e7e8n"));
+        assert_eq!(uci(&gs, "e8"), None);
+
+        let gs = BitBoardGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(uci(&gs, "O-O").as_deref(), Some("e1g1"));
+        assert_eq!(uci(&gs, "O-O-O").as_deref(), Some("e1c1"));
+    }
+
+    #[test]
+    fn lan_formatting() {
+        let lan = |gs: &BitBoardGame, uci: &str| gs.move_from_uci(uci).unwrap().to_lan(gs);
+        let gs = BitBoardGame::new();
+        assert_eq!(lan(&gs, "g1f3"), "Ng1-f3");
+        assert_eq!(lan(&gs, "e2e4"), "e2-e4");
+
+        let gs = BitBoardGame::from_fen(
+            "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4",
+        )
+        .unwrap();
+        assert_eq!(lan(&gs, "b5c6"), "Bb5xc6");
+        assert_eq!(lan(&gs, "f3e5"), "Nf3xe5");
+        assert_eq!(lan(&gs, "e1g1"), "O-O");
+
+        let gs = BitBoardGame::from_fen("3r4/4P1k1/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(lan(&gs, "e7e8q"), "e7-e8=Q");
+        assert_eq!(lan(&gs, "e7d8n"), "e7xd8=N");
+        assert_eq!(lan(&gs, "e5d6"), "e5xd6");
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        // the queen is pinned on the diagonal and the bishop on the file
+        let gs = BitBoardGame::from_fen("4r1k1/8/8/b7/8/8/3QB3/4K3 w - - 0 1").unwrap();
+        let board = &gs.board;
+        assert_eq!(board.pinned_pieces(true), 1 << sq("d2") | 1 << sq("e2"));
+        assert_eq!(board.pinned_pieces(false), 0);
+
+        let diagonal = ["d2", "c3", "b4", "a5"];
+        let file = ["e2", "e3", "e4", "e5", "e6", "e7", "e8"];
+        let mask = |squares: &[&str]| squares.iter().fold(0, |acc, s| acc | 1 << sq(s));
+        assert_eq!(board.pin_ray(sq("d2"), true), mask(&diagonal));
+        assert_eq!(board.pin_ray(sq("e2"), true), mask(&file));
+        assert_eq!(board.pin_ray(sq("e1"), true), 0);
+        assert_eq!(board.pin_ray(sq("a5"), false), 0);
+
+        // the queen only moves along its ray and the bishop can't move
+        let mut pinned_moves: Vec<_> = gs
+            .moves()
+            .iter()
+            .map(|mov| mov.to_uci())
+            .filter(|uci| uci.starts_with("d2") || uci.starts_with("e2"))
+            .collect();
+        pinned_moves.sort();
+        assert_eq!(pinned_moves, ["d2a5", "d2b4", "d2c3"]);
+    }
+
+    #[test]
+    fn shared_gen_masks() {
+        for fen in REFERENCE_FENS {
+            let board = BitBoardGame::from_fen(fen).unwrap().board;
+            for turn in [true, false] {
+                let masks = board.gen_masks(turn);
+                assert_eq!(masks.check, board.check_mask(turn), "{fen}");
+                assert_eq!(masks.ortho_pins, board.ortho_pin_mask(turn), "{fen}");
+                assert_eq!(masks.diagonal_pins, board.diagonal_pin_mask(turn), "{fen}");
+                assert_eq!(masks.enemy_attacks, board.attack_mask(!turn), "{fen}");
+            }
+        }
+
+        // perft regression over positions with pins, checks, promotions,
+        // castling and en passant
+        let expected: [&[usize]; 6] = [
+            &[20, 400, 8902, 197281],
+            &[48, 2039, 97862, 4085603],
+            &[14, 191, 2812, 43238, 674624],
+            &[6, 264, 9467, 422333],
+            &[44, 1486, 62379, 2103487],
+            &[46, 2079, 89890, 3894594],
+        ];
+        for (fen, counts) in REFERENCE_FENS.iter().zip(expected) {
+            let mut gs = BitBoardGame::from_fen(fen).unwrap();
+            assert_eq!(perft_stats(&mut gs, counts.len()), counts, "{fen}");
+        }
+    }
+
+    #[test]
+    fn ray_tables_match_fill() {
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut boards: Vec<BitBoard> = REFERENCE_FENS
+            .iter()
+            .map(|fen| BitBoardGame::from_fen(fen).unwrap().board)
+            .collect();
+        for _ in 0..200 {
+            let mut gs = BitBoardGame::new();
+            for _ in 0..120 {
+                let Some(mov) = gs.random_move(&mut rng) else {
+                    break;
+                };
+                gs.do_move(&mov);
+                boards.push(gs.board.clone());
+            }
+        }
+        for board in boards {
+            let blockers = board.piece_mask();
+            for pieces in (0..64)
+                .map(|square| 1u64 << square)
+                .chain([board.col_diagonal_mask(true), board.col_ortho_mask(false)])
+            {
+                let fill = |shift_left: bool, step: u8, side: u64| match shift_left {
+                    true => BitBoard::sliding_mask::<true>(pieces, step, blockers, side),
+                    false => BitBoard::sliding_mask::<false>(pieces, step, blockers, side),
+                };
+                let diagonal = fill(true, 7, BitBoard::LEFT_SIDE)
+                    | fill(true, 9, BitBoard::RIGHT_SIDE)
+                    | fill(false, 9, BitBoard::LEFT_SIDE)
+                    | fill(false, 7, BitBoard::RIGHT_SIDE);
+                let ortho = fill(true, 1, BitBoard::RIGHT_SIDE)
+                    | fill(false, 1, BitBoard::LEFT_SIDE)
+                    | fill(true, 8, 0)
+                    | fill(false, 8, 0);
+                assert_eq!(board.diagonal_like_attack_mask(pieces), diagonal);
+                assert_eq!(board.ortho_like_attack_mask(pieces), ortho);
+            }
+        }
+    }
+
+    #[test]
+    fn square_mapping() {
+        assert_eq!(square_index(0, 0), 7);
+        assert_eq!(square_index(7, 0), 0);
+        assert_eq!(square_index(0, 7), 63);
+        assert_eq!(square_index(7, 7), 56);
+        for square in 0..64 {
+            let (file, rank) = square_coords(square);
+            assert_eq!(square_index(file, rank), square);
+        }
+
+        let gs = BitBoardGame::new();
+        assert_eq!(gs.board.nibble(square_index(4, 0)), 0b1111);
+        assert_eq!(gs.board.nibble(square_index(0, 7)), 0b0010);
+        let e2e4 = square_index(4, 1) as u16 | (square_index(4, 3) as u16) << 6;
+        assert_eq!(e2e4.to_uci(), "e2e4");
+        assert!(gs.moves().iter().any(|mov| mov.mov == e2e4));
+    }
+
+    #[test]
+    fn checkers() {
+        let mask = |names: &[&str]| names.iter().fold(0, |acc, name| acc | 1 << sq(name));
+        let start = BitBoardGame::new();
+        assert_eq!(start.board.checkers(true), 0);
+        assert_eq!(start.board.checkers(false), 0);
+
+        let single = BitBoardGame::from_fen("4k3/8/8/1B6/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(single.board.checkers(false), mask(&["b5"]));
+        assert!(!single.board.is_double_check(false));
+        assert_eq!(single.board.checkers(true), 0);
+
+        let double = BitBoardGame::from_fen("4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1").unwrap();
+        assert_eq!(double.board.checkers(false), mask(&["d6", "e1"]));
+        assert!(double.board.is_double_check(false));
+        let king = double.board.col_king_mask(false);
+        for mov in double.moves() {
+            assert_eq!(1 << (mov.mov & 63), king, "{}", mov.to_uci());
+        }
+    }
+
+    #[test]
+    fn attackers() {
+        let gs = BitBoardGame::from_fen(REFERENCE_FENS[1]).unwrap();
+        let board = &gs.board;
+        let mask = |names: &[&str]| names.iter().fold(0, |acc, name| acc | 1 << sq(name));
+        assert_eq!(board.attackers_to(sq("f7"), true), mask(&["e5"]));
+        assert_eq!(
+            board.attackers_to(sq("d5"), false),
+            mask(&["b6", "e6", "f6"])
+        );
+        assert_eq!(board.attackers_to(sq("e5"), false), 0);
+        assert_eq!(board.attackers_to(sq("g2"), false), mask(&["h3"]));
+        assert_eq!(board.attackers_to(sq("g2"), true), mask(&["f3"]));
+        assert_eq!(board.attackers_to(sq("c3"), false), mask(&["b4"]));
+        assert_eq!(
+            board.attackers_to(sq("f1"), true),
+            mask(&["e1", "e2", "h1"])
+        );
+        assert_eq!(
+            board.attackers_to(sq("d8"), false),
+            mask(&["a8", "e7", "e8"])
+        );
+        for square in 0..64 {
+            for by_white in [true, false] {
+                assert_eq!(
+                    board.is_attacked(square, by_white),
+                    board.attackers_to(square, by_white) != 0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn king_zone_attacks() {
+        let gs = BitBoardGame::from_fen("6k1/5ppp/3b4/8/6nq/8/5PPP/5RK1 w - - 0 1").unwrap();
+        let board = &gs.board;
+        assert_eq!(board.attack_count(sq("h2"), false), 3);
+        assert_eq!(board.attack_count(sq("h2"), true), 1);
+        assert_eq!(board.attack_count(sq("f2"), false), 2);
+        assert_eq!(board.attack_count(sq("g1"), false), 0);
+        assert_eq!(board.king_zone_pressure(true), 5);
+        assert_eq!(board.king_zone_pressure(false), 0);
+        let start = BitBoardGame::new();
+        assert_eq!(start.board.king_zone_pressure(true), 0);
+        assert_eq!(start.board.king_zone_pressure(false), 0);
+    }
+
+    #[test]
+    fn attack_map_matches_squares() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "6k1/5ppp/3b4/8/6nq/8/5PPP/5RK1 w - - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let board = BitBoardGame::from_fen(fen).unwrap().board;
+            for by_white in [true, false] {
+                let attacked = (0..64)
+                    .filter(|&square| board.is_attacked(square, by_white))
+                    .count();
+                let map = board.attack_map(by_white);
+                assert_eq!(map.count_ones() as usize, attacked, "{fen}");
+                let defended = board.defended_squares(by_white);
+                assert_eq!(defended & !board.col_piece_mask(by_white), 0);
+                assert_eq!(defended & !map, 0);
+            }
+        }
+        let board = BitBoardGame::new().board;
+        // every white piece but the rooks is defended at the start
+        assert_eq!(
+            board.defended_squares(true),
+            board.col_piece_mask(true) & !(1 << sq("a1") | 1 << sq("h1"))
+        );
+        assert_eq!(board.attack_map(true).count_ones(), 22);
+    }
+
+    #[test]
+    fn static_exchange() {
+        let see = |fen: &str, from: &str, to: &str| {
+            BitBoardGame::from_fen(fen)
+                .unwrap()
+                .board
+                .see(sq(from), sq(to))
+        };
+        let pawns = "4k3/8/3p4/4p3/3P4/8/8/4K3 w - - 0 1";
+        assert_eq!(see(pawns, "d4", "e5"), 0);
+        assert_eq!(see("4k3/8/8/4p3/3P4/8/8/4K3 w - - 0 1", "d4", "e5"), 100);
+        assert_eq!(see("4k3/8/3p4/4p3/8/5N2/8/4K3 w - - 0 1", "f3", "e5"), -200);
+        // the rook behind joins once the first one has taken
+        let battery = "4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1";
+        assert_eq!(see(battery, "e2", "e5"), 100);
+        let defended = "4r1k1/8/3p4/4p3/8/8/4R3/4Q1K1 w - - 0 1";
+        assert_eq!(see(defended, "e2", "e5"), -400);
+        // black capturing
+        assert_eq!(see(pawns, "e5", "d4"), 100);
+    }
+
+    #[test]
+    fn material_balance() {
+        let gs = BitBoardGame::new();
+        assert_eq!(gs.piece_counts(), [[8, 2, 2, 2, 1, 1]; 2]);
+        assert_eq!(gs.material(true), 39);
+        assert_eq!(gs.material(true), gs.material(false));
+
+        let gs = BitBoardGame::from_fen("8/8/4k3/7p/8/2B5/1P6/1R2K3 w - - 0 1").unwrap();
+        assert_eq!(gs.piece_counts(), [[1, 0, 1, 1, 0, 1], [1, 0, 0, 0, 0, 1]]);
+        assert_eq!(gs.material(true), 9);
+        assert_eq!(gs.material(false), 1);
+        let centipawns = [100, 320, 330, 500, 900, 0];
+        assert_eq!(gs.material_weighted(true, &centipawns), 930);
+    }
+
+    #[test]
+    fn incremental_material() {
+        let mut rng = ChaCha20Rng::seed_from_u64(11);
+        for fen in REFERENCE_FENS {
+            for _ in 0..50 {
+                let mut gs = BitBoardGame::from_fen(fen).unwrap();
+                for _ in 0..200 {
+                    let counted = gs.material(true) - gs.material(false);
+                    assert_eq!(gs.incremental_material(), counted, "{}", gs.to_fen());
+                    assert_eq!(gs.flipped().incremental_material(), -counted);
+                    let Some(mov) = gs.random_move(&mut rng) else {
+                        break;
+                    };
+                    gs.do_move(&mov);
+                }
+            }
+        }
+
+        // promotions gain the new piece less the pawn
+        let gs = BitBoardGame::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(gs.incremental_material(), -2);
+        let mut promoted = gs.clone();
+        promoted.do_move(&gs.move_from_uci("a7b8q").unwrap());
+        assert_eq!(promoted.incremental_material(), 9);
+
+        // generated successors carry it too, so adopting one directly as
+        // `play_uci` does keeps it right for the next `do_move`
+        let mut gs = BitBoardGame::new();
+        gs.play_uci(&["e2e4", "d7d5", "e4d5"]).unwrap();
+        assert_eq!(gs.incremental_material(), 1);
+        gs.do_move(&gs.move_from_uci("d8d5").unwrap());
+        let counted = gs.material(true) - gs.material(false);
+        assert_eq!(gs.incremental_material(), counted);
+        for mov in gs.moves() {
+            let counted = mov.bbg.material(true) - mov.bbg.material(false);
+            assert_eq!(mov.bbg.incremental_material(), counted, "{}", mov.to_uci());
+        }
+    }
+
+    #[test]
+    fn attack_delta_open_file() {
+        let gs = BitBoardGame::from_fen("4k3/8/8/8/8/8/1P6/1R5K w - - 0 1").unwrap();
+        let mov = gs
+            .moves()
+            .into_iter()
+            .find(|mov| mov.to_uci() == "b1d1")
+            .unwrap();
+        let (gained, lost) = gs.attack_delta(&mov);
+        assert_eq!(gained & D_FILE, D_FILE & !(1 << 4));
+        // b2 is no longer defended and the rook now stands on d1
+        assert_eq!(lost, (1 << 14) | (1 << 4));
+    }
+
+    #[test]
+    fn grouped_moves() {
+        let gs = BitBoardGame::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        )
+        .unwrap();
+        let (checks, captures, quiets) = gs.moves_grouped();
+        assert_eq!(
+            checks.len() + captures.len() + quiets.len(),
+            gs.moves().len()
+        );
+        let mut checks: Vec<_> = checks.iter().map(|mov| mov.to_uci()).collect();
+        checks.sort();
+        assert_eq!(checks, ["c4f7", "h5e5", "h5f7"]);
+        let captures: Vec<_> = captures.iter().map(|mov| mov.to_uci()).collect();
+        assert_eq!(captures, ["h5h7"]);
+    }
+
+    fn san_of(fen: &str, uci: &str) -> String {
+        let gs = BitBoardGame::from_fen(fen).unwrap();
+        let mov = gs.moves().into_iter().find(|mov| mov.to_uci() == uci);
+        gs.san(&mov.unwrap())
+    }
+
+    #[test]
+    fn san_suffixes() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        assert_eq!(san_of(fen, "h5f7"), "Qxf7#");
+        assert_eq!(san_of(fen, "c4f7"), "Bxf7+");
+        assert_eq!(san_of(fen, "h5h7"), "Qxh7");
+        assert_eq!(san_of(fen, "g1f3"), "Nf3");
+        assert_eq!(san_of(fen, "e1f1"), "Kf1");
+        assert_eq!(san_of(fen, "d2d4"), "d4");
+    }
+
+    #[test]
+    fn san_disambiguation() {
+        let knights = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_eq!(san_of(knights, "b1d2"), "Nbd2");
+        assert_eq!(san_of(knights, "f3d2"), "Nfd2");
+        let rooks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san_of(rooks, "a1a3"), "R1a3");
+        let queens = "4k3/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1";
+        assert_eq!(san_of(queens, "a4d4"), "Qa4d4");
+        assert_eq!(san_of("r3k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), "O-O");
+        assert_eq!(san_of("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1"), "O-O-O+");
+        assert_eq!(
+            san_of("3rk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8q"),
+            "exd8=Q+"
+        );
+    }
+
+    #[test]
+    fn san_map_of_legal_moves() {
+        let gs = BitBoardGame::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        let map = gs.legal_san_map();
+        assert_eq!(map.len(), gs.moves().len());
+        assert_eq!(map["b1d2"], "Nbd2");
+        assert_eq!(map["f3d2"], "Nfd2");
+        assert_eq!(map["b1c3"], "Nc3");
+        assert_eq!(map["f3e5"], "Ne5");
+        let mate = BitBoardGame::from_fen("6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1").unwrap();
+        assert_eq!(mate.legal_san_map()["d1d8"], "Rd8#");
+    }
+
+    #[test]
+    fn fen_king_counts() {
+        assert!(BitBoardGame::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(BitBoardGame::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").is_none());
+        assert!(BitBoardGame::from_fen("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").is_none());
+        assert!(BitBoardGame::from_fen("2k1k3/8/8/8/8/8/8/4K3 b - - 0 1").is_none());
+        assert!(BitBoardGame::from_fen("4k2P/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(BitBoardGame::from_fen("4k3/8/8/8/8/8/8/p3K3 w - - 0 1").is_none());
+        assert!(BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_some());
+    }
+
+    #[test]
+    fn fen_optional_fields() {
+        let full = BitBoardGame::from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+        )
+        .unwrap();
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq",
+            " r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 ",
+        ] {
+            let short = BitBoardGame::from_fen(fen).unwrap();
+            assert!(short == full, "{fen}");
+            assert_eq!(short.to_fen(), full.to_fen());
+        }
+        let ep = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6").unwrap();
+        assert_eq!(ep.capturable_ep(), Some(sq("d6")));
+        assert_eq!(ep.to_fen(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        // the turn and castling fields are still needed
+        assert!(BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4K3 w").is_none());
+        assert!(BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4K3").is_none());
+    }
+
+    #[test]
+    fn fen_en_passant_square() {
+        let white = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(white.capturable_ep(), Some(sq("d6")));
+        assert!(white.moves().iter().any(|mov| mov.to_uci() == "e5d6"));
+        let black = BitBoardGame::from_fen("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1").unwrap();
+        assert_eq!(black.capturable_ep(), Some(sq("d3")));
+        assert!(black.moves().iter().any(|mov| mov.to_uci() == "e4d3"));
+
+        for fen in [
+            // rank for the wrong side to move
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 2",
+            "4k3/8/8/8/3Pp3/8/8/4K3 b - d6 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d5 0 2",
+            // no pawn that could have just moved two
+            "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 2",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - i6 0 2",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d 0 2",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d66 0 2",
+        ] {
+            assert!(BitBoardGame::from_fen(fen).is_none(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn build_position() {
+        use crate::piece::ColouredPiece::*;
+        let board = BitBoard::empty()
+            .with_piece(sq("e8"), BlackKing)
+            .with_piece(sq("d5"), BlackPawn)
+            .with_piece(sq("e5"), WhitePawn)
+            .with_piece(sq("a1"), WhiteRook)
+            .with_piece(sq("e1"), WhiteKing)
+            .with_piece(sq("b2"), WhiteQueen)
+            .with_piece(sq("c7"), BlackKnight)
+            .with_piece(sq("h7"), BlackBishop);
+        let gs = BitBoardGame::from_board(board, true, (true, false, false, false), Some(sq("d6")));
+        let fen = "4k3/2n4b/8/3pP3/8/8/1Q6/R3K3 w Q d6 0 1";
+        assert_eq!(gs.to_fen(), fen);
+        assert!(gs == BitBoardGame::from_fen(fen).unwrap());
+        assert_eq!(BitBoard::empty().piece_mask(), 0);
+
+        // placing replaces what was on the square
+        let board = BitBoard::empty()
+            .with_piece(sq("a1"), WhiteQueen)
+            .with_piece(sq("a1"), BlackKnight);
+        assert_eq!(board.to_string().lines().last(), Some("n-------"));
+    }
+
+    #[test]
+    fn board_validation() {
+        let mut gs = BitBoardGame::new();
+        assert_eq!(gs.board.validate(), Ok(()));
+        let mov = gs.moves().into_iter().next().unwrap();
+        gs.do_move(&mov);
+        assert_eq!(gs.board.validate(), Ok(()));
+
+        // a lone colour bit on e4
+        let mut board = BitBoardGame::new().board.clone();
+        board.board[3] |= 1 << sq("e4");
+        assert_eq!(
+            board.validate(),
+            Err(BoardError::BadPiece {
+                square: sq("e4"),
+                nibble: 0b1000
+            })
+        );
+
+        // turning the d1 queen (1011) into 1110
+        let mut board = BitBoardGame::new().board.clone();
+        board.board[0] &= !(1 << sq("d1"));
+        board.board[2] |= 1 << sq("d1");
+        assert_eq!(
+            board.validate(),
+            Err(BoardError::BadPiece {
+                square: sq("d1"),
+                nibble: 0b1110
+            })
+        );
+
+        // clearing bits 0 and 2 of the e8 king (0111) leaves 0010, a third
+        // black rook and no black king
+        let mut board = BitBoardGame::new().board.clone();
+        board.board[0] &= !(1 << sq("e8"));
+        board.board[2] &= !(1 << sq("e8"));
+        assert_eq!(
+            board.validate(),
+            Err(BoardError::KingCount {
+                white: false,
+                count: 0
+            })
+        );
+    }
+
+    #[test]
+    fn play_uci_sequence() {
+        let mut gs = BitBoardGame::new();
+        let scholars = ["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"];
+        assert_eq!(gs.play_uci(&scholars), Ok(()));
+        assert_eq!(
+            gs.to_fen(),
+            "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4"
+        );
+        assert!(gs.moves().is_empty());
+
+        let mut gs = BitBoardGame::new();
+        assert_eq!(gs.play_uci(&["e2e4", "e7e5", "e4e5", "d7d5"]), Err(2));
+        assert_eq!(
+            gs.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+    }
+
+    #[test]
+    fn single_move_legality() {
+        let find = |gs: &BitBoardGame, uci: &str| {
+            gs.moves()
+                .into_iter()
+                .find(|mov| mov.to_uci() == uci)
+                .unwrap()
+        };
+        // every generated move is legal in its own position
+        for fen in REFERENCE_FENS {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            assert!(gs.moves().iter().all(|mov| gs.is_legal(mov)), "{fen}");
+        }
+
+        // the d2 knight is pinned by the b4 bishop in the second position
+        let free = BitBoardGame::from_fen("4k3/8/8/8/1b6/8/4N3/4K3 w - - 0 1").unwrap();
+        let pinned = BitBoardGame::from_fen("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1").unwrap();
+        let knight = find(&free, "e2c3");
+        assert!(free.is_legal(&knight));
+        let pinned_knight = BitBoardGameMove {
+            mov: (sq("d2") as u16) | (sq("c4") as u16) << 6,
+            bbg: BitBoardGame::from_fen("4k3/8/8/8/1bN5/8/8/4K3 b - - 1 1").unwrap(),
+            castle: false,
+        };
+        assert!(!pinned.is_legal(&pinned_knight));
+        assert!(pinned
+            .moves()
+            .iter()
+            .all(|mov| !mov.to_uci().starts_with("d2")));
+
+        // moves from other positions
+        let start = BitBoardGame::new();
+        assert!(!start.is_legal(&knight));
+        let mut after_e4 = start.clone();
+        after_e4.play_uci(&["e2e4"]).unwrap();
+        assert!(!after_e4.is_legal(&find(&start, "d2d4")));
+        assert!(!start.is_legal(&find(&after_e4, "e7e5")));
+        let castle = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let castle_move = find(&castle, "e1g1");
+        assert!(castle.is_legal(&castle_move));
+        let no_rights = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        assert!(!no_rights.is_legal(&castle_move));
+
+        // same pieces but other rights, en passant square or clocks
+        let fen = "r3k2r/8/8/8/8/8/4P3/R3K2R w - - 37 60";
+        let quiet = BitBoardGame::from_fen(fen).unwrap();
+        assert!(quiet.is_legal(&find(&quiet, "e2e4")));
+        for other in [
+            "r3k2r/8/8/8/8/8/4P3/R3K2R w KQkq - 37 60",
+            "r3k2r/8/8/8/8/8/4P3/R3K2R w - - 0 1",
+            "r3k2r/8/8/8/8/8/4P3/R3K2R w - - 37 59",
+        ] {
+            let other = BitBoardGame::from_fen(other).unwrap();
+            for uci in ["e2e4", "a1b1", "e1f1"] {
+                assert!(!quiet.is_legal(&find(&other, uci)), "{uci}");
+            }
+        }
+        let mut played = quiet.clone();
+        assert!(played.play_uci(&["e1f1"]).is_ok());
+        assert_eq!(played.to_fen(), "r3k2r/8/8/8/8/8/4P3/R4K1R b - - 38 60");
+    }
+
+    #[test]
+    fn position_accessors() {
+        let gs = BitBoardGame::from_fen("r3k2r/8/8/8/3pP3/8/8/R3K2R b Kq e3 0 1").unwrap();
+        assert_eq!(gs.turn(), PlayerColour::Black);
+        assert_eq!(gs.castling_rights(), (false, true, true, false));
+        assert_eq!(gs.ep_square(), Some(sq("e3")));
+        assert_eq!(gs.capturable_ep(), Some(sq("e3")));
+
+        let gs = BitBoardGame::new();
+        assert_eq!(gs.turn(), PlayerColour::White);
+        assert_eq!(gs.castling_rights(), (true, true, true, true));
+        assert_eq!(gs.ep_square(), None);
+    }
+
+    #[test]
+    fn chess960_perft() {
+        // https://www.chessprogramming.org/Chess960_Perft_Results
+        let cases = [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                [21, 528, 12189],
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                [21, 807, 18002],
+            ),
+            (
+                "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+                [20, 479, 10471],
+            ),
+            (
+                "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+                [22, 593, 13440],
+            ),
+        ];
+        for (fen, counts) in cases {
+            let mut gs = BitBoardGame::from_fen_960(fen).unwrap();
+            assert_eq!(gs.to_fen(), fen);
+            for (depth, count) in counts.into_iter().enumerate() {
+                assert_eq!(
+                    perft(&mut gs, depth + 1),
+                    count,
+                    "{fen} depth {}",
+                    depth + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chess960_ids() {
+        let standard = BitBoardGame::from_960_id(518).unwrap();
+        assert_eq!(
+            standard.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+        assert!(standard.board == BitBoardGame::new().board);
+        assert_eq!(standard.legal_uci(), BitBoardGame::new().legal_uci());
+
+        assert_eq!(
+            BitBoardGame::from_960_id(0).unwrap().to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+        assert_eq!(
+            BitBoardGame::from_960_id(959).unwrap().to_fen(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1"
+        );
+        assert!(BitBoardGame::from_960_id(960).is_none());
+    }
+
+    #[test]
+    fn chess960_castles() {
+        let gs = BitBoardGame::from_fen("r3k2r/8/8/8/8/8/8/RK5R w HAha - 0 1").unwrap();
+        let castle = |uci: &str| gs.moves().into_iter().find(|mov| mov.to_uci() == uci);
+        let qs = castle("b1a1").unwrap();
+        assert_eq!(qs.bbg.to_fen(), "r3k2r/8/8/8/8/8/8/2KR3R b ha - 1 1");
+        assert_eq!(gs.san(&qs), "O-O-O");
+        let ks = castle("b1h1").unwrap();
+        assert_eq!(ks.bbg.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b ha - 1 1");
+        assert_eq!(gs.san(&ks), "O-O");
+
+        // the king may already stand on its destination
+        let gs = BitBoardGame::from_fen_960("6k1/8/8/8/8/8/8/6KR w K - 0 1").unwrap();
+        let castle = gs.moves().into_iter().find(|mov| mov.to_uci() == "g1h1");
+        assert_eq!(
+            castle.unwrap().bbg.to_fen(),
+            "6k1/8/8/8/8/8/8/5RK1 b - - 1 1"
+        );
+        // but not while the rook shields it from a rook on the far side
+        let gs = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/rRK5 w B - 0 1").unwrap();
+        assert!(gs.moves().iter().all(|mov| mov.to_uci() != "c1b1"));
+    }
+
+    #[test]
+    fn file_letter_castling() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w";
+        for (letters, standard) in [("AHah", "KQkq"), ("Ha", "Kq"), ("A", "Q")] {
+            let gs = BitBoardGame::from_fen(&format!("{start} {letters} - 0 1")).unwrap();
+            let expected = BitBoardGame::from_fen(&format!("{start} {standard} - 0 1")).unwrap();
+            assert!(gs == expected, "{letters}");
+            assert_eq!(gs.to_fen(), expected.to_fen());
+        }
+        let gs = BitBoardGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w AHah - 0 1").unwrap();
+        assert_eq!(perft(&mut gs.clone(), 3), 13744);
+        assert_eq!(gs.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+        // explicitly Chess960 games keep their rook files
+        let gs = BitBoardGame::from_fen_960(&format!("{start} AHah - 0 1")).unwrap();
+        assert_eq!(gs.castling_rights(), (true, true, true, true));
+        assert!(gs != BitBoardGame::new());
+    }
+
+    #[test]
+    fn dash_castling_field() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1";
+        for gs in [
+            BitBoardGame::from_fen(fen).unwrap(),
+            BitBoardGame::from_fen_960(fen).unwrap(),
+        ] {
+            assert_eq!(gs.castling_rights(), (false, false, false, false));
+            assert_eq!(gs.to_fen(), fen);
+            assert!(gs.moves().iter().all(|mov| !mov.castle));
+            let king_moves: Vec<_> = gs
+                .moves()
+                .iter()
+                .map(|mov| mov.to_uci())
+                .filter(|uci| uci.starts_with("e1"))
+                .collect();
+            assert_eq!(king_moves.len(), 5, "{king_moves:?}");
+        }
+        let black = BitBoardGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b - - 0 1").unwrap();
+        assert!(black.moves().iter().all(|mov| !mov.castle));
+        assert_eq!(perft(&mut BitBoardGame::from_fen(fen).unwrap(), 1), 24);
+    }
+
+    #[test]
+    fn flip_symmetry() {
+        for fen in REFERENCE_FENS {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            let mut flipped = gs.flipped();
+            assert_ne!(flipped.to_fen(), gs.to_fen());
+            assert_eq!(flipped.flipped().to_fen(), gs.to_fen());
+            assert_eq!(perft(&mut flipped, 3), perft(&mut gs.clone(), 3), "{fen}");
+        }
+        let gs = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 1").unwrap();
+        assert_eq!(gs.flipped().to_fen(), "r3k3/8/8/8/3Pp3/8/8/4K3 b q d3 0 1");
+    }
+
+    #[test]
+    fn move_list_matches_moves() {
+        let mut buf = MoveList::new();
+        let chess960 = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        for fen in REFERENCE_FENS.iter().chain([&chess960]) {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            for mov in gs.moves().iter().take(8) {
+                let mut expected: Vec<_> = mov
+                    .bbg
+                    .moves()
+                    .iter()
+                    .map(|m| (m.to_uci(), m.bbg.to_fen()))
+                    .collect();
+                mov.bbg.moves_into(&mut buf);
+                let mut found: Vec<_> = buf.iter().map(|m| (m.to_uci(), m.bbg.to_fen())).collect();
+                expected.sort();
+                found.sort();
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn castling_through_check() {
+        // (kingside, queenside) castles available to the side to move
+        let castles = |fen: &str| {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            let legal = gs.legal_uci();
+            let has = |uci: &str| legal.iter().any(|m| m == uci);
+            (has("e1g1") || has("e8g8"), has("e1c1") || has("e8c8"))
+        };
+        assert_eq!(castles("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, true));
+        // in check
+        assert_eq!(
+            castles("4k3/8/8/8/4r3/8/8/R3K2R w KQ - 0 1"),
+            (false, false)
+        );
+        // passing through f1 or d1
+        assert_eq!(castles("4k3/8/8/8/8/7b/8/R3K2R w KQ - 0 1"), (false, true));
+        assert_eq!(castles("3rk3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, false));
+        // landing on g1 or c1
+        assert_eq!(castles("4k1r1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (false, true));
+        assert_eq!(castles("2r1k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, false));
+        // the rook may pass an attacked b1 and start attacked on h1
+        assert_eq!(castles("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, true));
+        assert_eq!(castles("4k2r/8/8/8/8/8/8/R3K2R w KQ - 0 1"), (true, true));
+
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1"), (true, true));
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/4RK2 b kq - 0 1"), (false, false));
+        assert_eq!(castles("r3k2r/8/7B/8/8/8/8/4K3 b kq - 0 1"), (false, true));
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/3RK3 b kq - 0 1"), (true, false));
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/4K1R1 b kq - 0 1"), (false, true));
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/2R1K3 b kq - 0 1"), (true, false));
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/1R2K3 b kq - 0 1"), (true, true));
+    }
+
+    #[test]
+    fn en_passant_discovered_check() {
+        let can_take = |fen: &str, uci: &str| {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            gs.legal_uci().iter().any(|m| m == uci)
+        };
+        // both pawns leave the rank, opening it between the king and the rook
+        assert!(!can_take("8/8/8/8/k2pP2R/8/8/4K3 b - e3 0 1", "d4e3"));
+        assert!(!can_take("8/8/8/8/R2pP2k/8/8/4K3 b - e3 0 1", "d4e3"));
+        assert!(!can_take("4k3/8/8/K2pP2r/8/8/8/8 w - d6 0 1", "e5d6"));
+        assert!(!can_take("4k3/8/8/q2pP2K/8/8/8/8 w - d6 0 1", "e5d6"));
+        assert!(!can_take("4k3/8/8/K3Pp1r/8/8/8/8 w - f6 0 1", "e5f6"));
+        // another piece still blocks the rank
+        assert!(can_take("8/8/8/8/k2pP1NR/8/8/4K3 b - e3 0 1", "d4e3"));
+        assert!(can_take("4k3/8/8/K1B1pP1r/8/8/8/8 w - e6 0 1", "f5e6"));
+        assert!(can_take("4k3/8/8/KB1pP2r/8/8/8/8 w - d6 0 1", "e5d6"));
+        // the king is off the rank
+        assert!(can_take("8/8/8/k7/3pP2R/8/8/4K3 b - e3 0 1", "d4e3"));
+        // the captured pawn is pinned on a diagonal instead
+        assert!(!can_take("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", "c5d6"));
+        assert!(!can_take("8/7k/8/8/3pP3/8/8/1B2K3 b - e3 0 1", "d4e3"));
+        assert!(can_take("8/7k/8/8/3pP3/8/8/1B2K3 b - e3 0 1", "d4d3"));
+        // another piece still blocks the diagonal
+        assert!(can_take("8/5bk1/4N3/2Pp4/8/1K6/8/8 w - d6 0 1", "c5d6"));
+    }
+
+    #[test]
+    fn captures_onto_square() {
+        let captures = |gs: &BitBoardGame, square: &str| {
+            let mut ucis: Vec<_> = gs
+                .captures_to(sq(square))
+                .iter()
+                .map(|m| m.to_uci())
+                .collect();
+            ucis.sort();
+            ucis
+        };
+        let gs = BitBoardGame::from_fen("4k3/8/2n5/3p4/2P1P3/2N2N2/1B6/3QK3 w - - 0 1").unwrap();
+        assert_eq!(captures(&gs, "d5"), ["c3d5", "c4d5", "d1d5", "e4d5"]);
+        assert!(captures(&gs, "d4").is_empty());
+        assert!(captures(&gs, "c6").is_empty());
+        let gs = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(captures(&gs, "d6"), ["e5d6"]);
+    }
+
+    #[test]
+    fn moves_of_one_piece_type() {
+        let ucis = |gs: &BitBoardGame, piece| {
+            let mut ucis: Vec<_> = gs
+                .moves_for_piece(piece)
+                .iter()
+                .map(|m| m.to_uci())
+                .collect();
+            ucis.sort();
+            ucis
+        };
+        let gs = BitBoardGame::new();
+        assert_eq!(ucis(&gs, Piece::Knight), ["b1a3", "b1c3", "g1f3", "g1h3"]);
+        assert_eq!(ucis(&gs, Piece::Pawn).len(), 16);
+        assert!(ucis(&gs, Piece::Queen).is_empty());
+
+        let gs = BitBoardGame::from_fen("4k3/1P6/8/8/8/8/8/R2QK2R w K - 0 1").unwrap();
+        assert_eq!(ucis(&gs, Piece::Pawn), ["b7b8b", "b7b8n", "b7b8q", "b7b8r"]);
+        assert!(ucis(&gs, Piece::King).contains(&"e1g1".to_string()));
+        assert!(ucis(&gs, Piece::Rook)
+            .iter()
+            .all(|uci| uci.starts_with(['a', 'h'])));
+        assert!(ucis(&gs, Piece::Queen)
+            .iter()
+            .all(|uci| uci.starts_with("d1")));
+        let total: usize = [
+            Piece::Pawn,
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+            Piece::King,
+        ]
+        .into_iter()
+        .map(|piece| gs.moves_for_piece(piece).len())
+        .sum();
+        assert_eq!(total, gs.moves().len());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let chess960 = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        for fen in REFERENCE_FENS.iter().chain([&chess960]) {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            let json = serde_json::to_string(&gs).unwrap();
+            assert_eq!(json, format!("\"{}\"", gs.to_fen()));
+            let back: BitBoardGame = serde_json::from_str(&json).unwrap();
+            assert_eq!(back.to_fen(), gs.to_fen());
+        }
+        assert!(serde_json::from_str::<BitBoardGame>("\"not a fen\"").is_err());
+    }
+
+    #[test]
+    fn reused_vec_matches_moves() {
+        let mut buf = Vec::with_capacity(240);
+        let storage = buf.as_ptr();
+        for fen in REFERENCE_FENS {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            for mov in gs.moves() {
+                mov.bbg.moves_reuse(&mut buf);
+                let expected: Vec<_> = mov.bbg.moves().iter().map(|m| m.to_uci()).collect();
+                let found: Vec<_> = buf.iter().map(|m| m.to_uci()).collect();
+                assert_eq!(found, expected);
+            }
+        }
+        assert_eq!(buf.as_ptr(), storage);
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in REFERENCE_FENS {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            assert_eq!(
+                BitBoardGame::from_fen(&gs.to_fen()).unwrap().to_fen(),
+                gs.to_fen()
+            );
+        }
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        assert_eq!(BitBoardGame::from_fen(fen).unwrap().to_fen(), fen);
+    }
+
+    #[test]
+    fn move_clocks() {
+        let mut gs = BitBoardGame::new();
+        for uci in ["g1f3", "g8f6", "f3g1", "e7e5"] {
+            let mov = gs.moves().into_iter().find(|m| m.to_uci() == uci).unwrap();
+            gs.do_move(&mov);
+        }
+        assert_eq!(
+            gs.to_fen(),
+            "rnbqkb1r/pppp1ppp/5n2/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 3"
+        );
+    }
+
+    #[test]
+    fn strict_perft_reference() {
+        for fen in REFERENCE_FENS {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            let mut game = gs.clone();
+            assert_eq!(gs.perft_strict(3), Ok(perft(&mut game, 3)));
+        }
+    }
+
+    #[test]
+    fn strict_perft_reports_illegal() {
+        // also steps the king one file towards a, ignoring whether it's attacked
+        let careless_king = |gs: &BitBoardGame| {
+            let mut moves = gs.moves();
+            let king = gs.board.col_king_mask(gs.turn).trailing_zeros() as u8;
+            if king & 7 != 7 && gs.board.piece_mask() & (1 << (king + 1)) == 0 {
+                let mut bbg = gs.clone();
+                bbg.board.mov(king, king + 1);
+                bbg.turn = !bbg.turn;
+                let mov = (((king + 1) as u16) << 6) + king as u16;
+                moves.push(BitBoardGameMove {
+                    mov,
+                    bbg,
+                    castle: false,
+                });
+            }
+            moves
+        };
+        let fen = "3rk3/8/8/8/8/8/8/4K3 w - - 0 1";
+        let gs = BitBoardGame::from_fen(fen).unwrap();
+        assert_eq!(
+            gs.perft_strict_with(2, &careless_king),
+            Err(IllegalMove {
+                fen: fen.to_string(),
+                uci: "e1d1".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn promotion_uci() {
+        let gs = BitBoardGame::from_fen("k7/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut promotions: Vec<_> = gs
+            .moves()
+            .iter()
+            .map(|mov| mov.to_uci())
+            .filter(|uci| uci.starts_with("e7e8"))
+            .collect();
+        promotions.sort();
+        assert_eq!(promotions, ["e7e8b", "e7e8n", "e7e8q", "e7e8r"]);
+    }
+
+    #[test]
+    fn attack_rendering() {
+        let gs = BitBoardGame::from_fen("4k3/8/8/3p4/8/2N5/8/4K3 w - - 0 1").unwrap();
+        let expected = concat!(
+            " -  -  -  -  k  -  -  - \n",
+            " -  -  -  -  -  -  -  - \n",
+            " -  -  -  -  -  -  -  - \n",
+            " - [-] - [p] -  -  -  - \n",
+            "[-] -  -  - [-] -  -  - \n",
+            " -  -  N  -  -  -  -  - \n",
+            "[-] -  - [-][-][-] -  - \n",
+            " - [-] - [-] K [-] -  - \n",
+        );
+        assert_eq!(gs.board.attack_string(true), expected);
+    }
+
+    fn play(ucis: &[&str]) -> BitBoardGame {
+        let mut gs = BitBoardGame::new();
+        for uci in ucis {
+            let mov = gs.moves().into_iter().find(|m| m.to_uci() == *uci).unwrap();
+            gs.do_move(&mov);
+        }
+        gs
+    }
+
+    #[test]
+    fn transpositions_equal() {
+        let a = play(&["g1f3", "g8f6", "b1c3", "b8c6"]);
+        let b = play(&["b1c3", "b8c6", "g1f3", "g8f6"]);
+        assert!(a == b);
+
+        // the clocks differ but the positions repeat
+        let c = play(&["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert!(c == BitBoardGame::new());
+
+        // e3 is recorded but no black pawn can take on it
+        let d = play(&["e2e4", "g8f6", "g1f3"]);
+        let e = play(&["g1f3", "g8f6", "e2e4"]);
+        assert_eq!(e.to_fen().split(' ').nth(3), Some("e3"));
+        assert!(d == e);
+
+        let f = play(&["b1c3", "b8c6", "g1f3", "g8f6", "c3b1"]);
+        assert!(a != f);
+        let mut set = HashSet::new();
+        set.insert(a);
+        assert!(set.contains(&b));
+        assert!(!set.contains(&f));
+    }
+
+    #[test]
+    fn capturable_ep_equality() {
+        let with = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let without = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 2").unwrap();
+        assert_eq!(with.capturable_ep(), Some(sq("d6")));
+        assert!(with != without);
+
+        let idle = BitBoardGame::from_fen("4k3/8/8/3p3P/8/8/8/4K3 w - d6 0 2").unwrap();
+        let idle2 = BitBoardGame::from_fen("4k3/8/8/3p3P/8/8/8/4K3 w - - 0 2").unwrap();
+        assert_eq!(idle.capturable_ep(), None);
+        assert!(idle == idle2);
+
+        let castling = BitBoardGame::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        let no_castling = BitBoardGame::from_fen("r3k3/8/8/8/8/8/8/4K2R w q - 0 1").unwrap();
+        assert!(castling != no_castling);
+    }
+
+    #[test]
+    fn null_move_round_trip() {
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        let mut gs = BitBoardGame::from_fen(fen).unwrap();
+        let undo = gs.make_null();
+        assert_eq!(
+            gs.to_fen(),
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 3"
+        );
+        assert_eq!(gs.turn(), PlayerColour::Black);
+        gs.unmake_null(undo);
+        assert_eq!(gs.to_fen(), fen);
+        assert!(gs.moves().iter().any(|m| m.to_uci() == "e5f6"));
+    }
+
+    #[test]
+    fn opponent_in_check() {
+        // white to move with black's king attacked by the queen
+        let illegal = "4k3/8/8/8/8/8/4Q3/4K3 w - - 0 1";
+        assert!(!BitBoardGame::from_fen(illegal).unwrap().is_legal_position());
+        assert!(BitBoardGame::from_fen_strict(illegal).is_none());
+        let illegal = "4k3/8/8/8/8/5n2/8/4K3 b - - 0 1";
+        assert!(BitBoardGame::from_fen_strict(illegal).is_none());
+
+        // the side to move may be in check
+        let legal = "4k3/8/8/8/8/8/4Q3/4K3 b - - 0 1";
+        assert!(BitBoardGame::from_fen(legal).unwrap().is_legal_position());
+        assert!(BitBoardGame::from_fen_strict(legal).is_some());
+        for fen in REFERENCE_FENS {
+            assert!(BitBoardGame::from_fen_strict(fen).is_some(), "{fen}");
+        }
+        assert!(BitBoardGame::from_fen_strict("not a fen").is_none());
+    }
+
+    #[test]
+    fn automatic_and_claimable_draws() {
+        // shuffle the knights out and back, every fourth ply repeats
+        let mut gs = BitBoardGame::new();
+        let mut history = Vec::new();
+        for _ in 0..4 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                history.push(gs.polyglot_hash());
+                gs = gs.move_from_uci(uci).unwrap().bbg;
+            }
+            let occurrences = history.len() / 4 + 1;
+            let status = gs.status(&history);
+            match occurrences {
+                2 => assert_eq!(status, None),
+                3 | 4 => assert_eq!(status, Some(GameStatus::ThreefoldRepetition)),
+                _ => assert_eq!(status, Some(GameStatus::FivefoldRepetition)),
+            }
+            assert_eq!(
+                gs.auto_draw(&history),
+                (occurrences >= 5).then_some(GameStatus::FivefoldRepetition)
+            );
+        }
+
+        let quiet = |clock: u16| format!("4k3/8/8/8/8/8/4P3/R3K3 w - - {clock} 90");
+        let gs = BitBoardGame::from_fen(&quiet(99)).unwrap();
+        assert_eq!(gs.status(&[]), None);
+        let gs = BitBoardGame::from_fen(&quiet(100)).unwrap();
+        assert_eq!(gs.status(&[]), Some(GameStatus::FiftyMoves));
+        assert_eq!(gs.auto_draw(&[]), None);
+        let gs = BitBoardGame::from_fen(&quiet(150)).unwrap();
+        assert_eq!(gs.status(&[]), Some(GameStatus::SeventyFiveMoves));
+        assert_eq!(gs.auto_draw(&[]), Some(GameStatus::SeventyFiveMoves));
+        // mate on the 75th move stands
+        let mated = BitBoardGame::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 120").unwrap();
+        assert_eq!(mated.status(&[]), Some(GameStatus::Checkmate));
+        assert_eq!(mated.auto_draw(&[]), None);
+
+        let stalemate = BitBoardGame::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(stalemate.auto_draw(&[]), Some(GameStatus::Stalemate));
+
+        for (fen, insufficient) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", true),
+            ("4kb2/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            // bishops on c1 and f8, both dark squares
+            ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1", false),
+            ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/2NNK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+        ] {
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            assert_eq!(gs.is_insufficient_material(), insufficient, "{fen}");
+            assert_eq!(
+                gs.auto_draw(&[]) == Some(GameStatus::InsufficientMaterial),
+                insufficient,
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn stalemate_and_checkmate() {
+        let stalemate = BitBoardGame::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.is_stalemate());
+        assert!(!stalemate.is_checkmate());
+
+        let back_rank = BitBoardGame::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(back_rank.is_checkmate());
+        assert!(!back_rank.is_stalemate());
+
+        let escapable = BitBoardGame::from_fen("3R2k1/5pp1/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(!escapable.is_checkmate());
+        assert!(!escapable.is_stalemate());
+        assert!(!BitBoardGame::new().is_stalemate());
+        assert!(!BitBoardGame::new().is_checkmate());
+    }
+
+    #[test]
+    fn in_check() {
+        let checked = [
+            "rnbqkbnr/ppppp2p/5p2/6pQ/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 3",
+            "4k3/8/8/8/8/5n2/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/5p2/4K3/8 w - - 0 1",
+            "4r3/8/8/8/8/8/8/2B1K1k1 w - - 0 1",
+            // double check
+            "4k3/8/8/8/1b6/5n2/8/4K3 w - - 0 1",
+        ];
+        for fen in checked {
+            assert!(BitBoardGame::from_fen(fen).unwrap().in_check(), "{fen}");
+        }
+        let quiet = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // white gives check but black is to move out of it, white is not
+            "rnbqkbnr/ppppp2p/5p2/6pQ/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 1 3",
+            "4r3/8/8/8/8/8/4B3/4K1k1 w - - 0 1",
+        ];
+        for fen in quiet {
+            assert!(!BitBoardGame::from_fen(fen).unwrap().in_check(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn random_self_play() {
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let mut gs = BitBoardGame::new();
+            // random games rarely end by mate so stop at the fifty move rule
+            for _ in 0..400 {
+                let fen = gs.to_fen();
+                let parsed = BitBoardGame::from_fen(&fen).unwrap();
+                assert_eq!(parsed.to_fen(), fen);
+                assert!(parsed == gs, "{fen}");
+                let Some(mov) = gs.random_move(&mut rng) else {
+                    break;
+                };
+                let undo = gs.do_move(&mov);
+                gs.unmove(&undo);
+                assert_eq!(gs.to_fen(), fen);
+                gs.do_move(&mov);
+                if gs.to_fen().split(' ').nth(4) == Some("100") {
+                    break;
+                }
+            }
+        }
+    }
+}
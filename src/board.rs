@@ -17,12 +17,18 @@ struct GSMetaData {
     /// Black queenside castle
     black_qs_castle: bool,
     enpasant_col: Option<u8>,
+    /// plies since the last capture or pawn move
+    halfmove: u16,
+    /// starts at 1 and goes up after each black move
+    fullmove: u16,
 }
 
 /// Fes Move Detailed
 /// move containing unpacked promotion and taking info
 #[derive(Debug, PartialEq, Clone)]
 pub struct FesMoveDet {
+    /// `GameState` square, rank * 8 + file with a1 = 0 and h1 = 7, the
+    /// reverse of the bitboard numbering in `bit_board::square_index`
     pub from: u8,
     pub to: u8,
     promo: Option<Piece>,
@@ -95,12 +101,21 @@ impl Display for FesMoveDet {
 }
 
 impl Move for FesMoveDet {
+    /// files count up from a in `GameState` squares, unlike the `u16`
+    /// bitboard moves where they count up from h
     fn to_uci(&self) -> String {
         let ox = ('a' as u8 + (self.from & 7) as u8) as char;
         let oy = ('1' as u8 + (self.from >> 3) as u8) as char;
         let nx = ('a' as u8 + (self.to & 7) as u8) as char;
         let ny = ('1' as u8 + (self.to >> 3) as u8) as char;
-        format!("{ox}{oy}{nx}{ny}")
+        let promo = match self.promo {
+            Some(Piece::Knight) => "n",
+            Some(Piece::Bishop) => "b",
+            Some(Piece::Rook) => "r",
+            Some(Piece::Queen) => "q",
+            _ => "",
+        };
+        format!("{ox}{oy}{nx}{ny}{promo}")
     }
 }
 
@@ -115,22 +130,28 @@ impl Board {
             pieces: [[None; 8]; 8],
         }
     }
+    /// Parse the piece placement field of a FEN, which must describe exactly
+    /// 8 ranks of 8 squares each
     pub fn from_fen(input: &str) -> Option<Self> {
         let mut board = Self {
             pieces: [[None; 8]; 8],
         };
+        let mut ranks = 0;
         for (i, line) in input.split('/').enumerate() {
             if i >= 8 {
                 return None;
             }
             let mut counter = 0;
             for c in line.chars() {
-                if counter > 8 {
-                    return None;
-                }
-                if c.is_digit(10) {
-                    counter += c as usize - '0' as usize;
+                if let Some(skip) = c.to_digit(10) {
+                    if skip == 0 || skip > 8 {
+                        return None;
+                    }
+                    counter += skip as usize;
                 } else {
+                    if counter >= 8 {
+                        return None;
+                    }
                     board.pieces[7 - i][counter] = match c {
                         'P' => Some(piece::ColouredPiece::WhitePawn),
                         'N' => Some(piece::ColouredPiece::WhiteKnight),
@@ -149,9 +170,73 @@ impl Board {
                     counter += 1;
                 }
             }
+            if counter != 8 {
+                return None;
+            }
+            ranks += 1;
+        }
+        if ranks != 8 {
+            return None;
         }
         Some(board)
     }
+
+    /// the piece placement field of a FEN, the inverse of `from_fen`
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for (i, row) in self.pieces.iter().rev().enumerate() {
+            if i != 0 {
+                fen.push('/');
+            }
+            let mut empty = 0;
+            for piece in row {
+                match piece {
+                    Some(p) => {
+                        if empty != 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push_str(&p.to_string());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty != 0 {
+                fen.push_str(&empty.to_string());
+            }
+        }
+        fen
+    }
+
+    /// true if a pawn of `capturer` stands ready to take en passant a pawn
+    /// that just moved two squares on file `col`
+    fn ep_capturable(&self, col: usize, capturer: PlayerColour) -> bool {
+        let rank = match capturer {
+            PlayerColour::White => 4,
+            PlayerColour::Black => 3,
+        };
+        let pawn = Some(ColouredPiece::from_parts(capturer, Piece::Pawn));
+        (col > 0 && self.pieces[rank][col - 1] == pawn)
+            || (col < 7 && self.pieces[rank][col + 1] == pawn)
+    }
+
+    /// exactly one king per side and no pawns on the first or last rank
+    fn is_valid_setup(&self) -> bool {
+        let count = |target| {
+            self.pieces
+                .iter()
+                .flatten()
+                .filter(|&&p| p == Some(target))
+                .count()
+        };
+        let back_rank_pawn = [self.pieces[0], self.pieces[7]]
+            .iter()
+            .flatten()
+            .any(|p| matches!(p.map(|p| p.piece()), Some(piece::Piece::Pawn)));
+        count(piece::ColouredPiece::WhiteKing) == 1
+            && count(piece::ColouredPiece::BlackKing) == 1
+            && !back_rank_pawn
+    }
 }
 
 #[derive(Clone)]
@@ -195,6 +280,10 @@ impl Display for GameState {
         }
         f.write_str(&format!("Castle rights: {castle_rights}\n"))?;
         f.write_str(&format!("ep: {:?}\n", self.meta.enpasant_col))?;
+        f.write_str(&format!(
+            "Halfmove clock: {}, fullmove: {}\n",
+            self.meta.halfmove, self.meta.fullmove
+        ))?;
         f.write_str("Player: ")?;
         f.write_str(match self.turn {
             PlayerColour::White => "white",
@@ -204,6 +293,52 @@ impl Display for GameState {
     }
 }
 
+impl GameState {
+    /// Play the legal move written as `uci`, returning what `unmove` needs
+    /// to take it back. None, leaving the position as it was, if there is no
+    /// such move
+    pub fn make_uci(&mut self, uci: &str) -> Option<FesMoveDet> {
+        let mov = self.moves().into_iter().find(|mov| mov.to_uci() == uci)?;
+        Some(self.do_move(&mov))
+    }
+
+    /// FEN string describing this position
+    pub fn to_fen(&self) -> String {
+        let mut fen = self.board.to_fen();
+        fen.push_str(match self.turn {
+            White => " w ",
+            Black => " b ",
+        });
+
+        let castle_len = fen.len();
+        for (right, c) in [
+            (self.meta.white_ks_castle, 'K'),
+            (self.meta.white_qs_castle, 'Q'),
+            (self.meta.black_ks_castle, 'k'),
+            (self.meta.black_qs_castle, 'q'),
+        ] {
+            if right {
+                fen.push(c);
+            }
+        }
+        if fen.len() == castle_len {
+            fen.push('-');
+        }
+
+        match self.meta.enpasant_col {
+            Some(col) => {
+                fen.push(' ');
+                fen.push((b'a' + col) as char);
+                fen.push(if self.turn == White { '6' } else { '3' });
+            }
+            None => fen.push_str(" -"),
+        }
+
+        fen.push_str(&format!(" {} {}", self.meta.halfmove, self.meta.fullmove));
+        fen
+    }
+}
+
 use ColouredPiece::*;
 use PlayerColour::*;
 
@@ -231,28 +366,47 @@ impl ChessGame for GameState {
     fn from_fen(input: &str) -> Option<Self> {
         let mut input_parts = input.trim().split(" ");
         let board = Board::from_fen(input_parts.next()?)?;
+        if !board.is_valid_setup() {
+            return None;
+        }
         let turn = match input_parts.next()? {
             "w" => PlayerColour::White,
             "b" => PlayerColour::Black,
             _ => return None,
         };
 
+        // castling may also name the rook files as in X-FEN, which only the
+        // a and h files can be in standard chess
         let castle_rights = input_parts.next()?;
-        let white_ks_castle = castle_rights.contains('K');
-        let white_qs_castle = castle_rights.contains('Q');
-        let black_ks_castle = castle_rights.contains('k');
-        let black_qs_castle = castle_rights.contains('q');
-
-        let enpasant_col = match input_parts.next()?.chars().next()? {
-            'a' => Some(0),
-            'b' => Some(1),
-            'c' => Some(2),
-            'd' => Some(3),
-            'e' => Some(4),
-            'f' => Some(5),
-            'g' => Some(6),
-            'h' => Some(7),
-            _ => None,
+        if castle_rights
+            .chars()
+            .any(|c| matches!(c.to_ascii_uppercase(), 'B'..='G'))
+        {
+            return None;
+        }
+        let white_ks_castle = castle_rights.contains(['K', 'H']);
+        let white_qs_castle = castle_rights.contains(['Q', 'A']);
+        let black_ks_castle = castle_rights.contains(['k', 'h']);
+        let black_qs_castle = castle_rights.contains(['q', 'a']);
+
+        // the square behind a pawn that just moved two, on the 6th rank when
+        // white is to move and the 3rd when black is
+        // the en passant square and move clocks are often left off
+        let enpasant_col = match input_parts.next().unwrap_or("-").as_bytes() {
+            b"-" => None,
+            &[file @ b'a'..=b'h', rank] if rank == if turn == White { b'6' } else { b'3' } => {
+                Some(file - b'a')
+            }
+            _ => return None,
+        };
+        let enpasant_col = enpasant_col.filter(|&col| board.ep_capturable(col as usize, turn));
+        let halfmove = match input_parts.next() {
+            Some(clock) => clock.parse().ok()?,
+            None => 0,
+        };
+        let fullmove = match input_parts.next() {
+            Some(clock) => clock.parse().ok()?,
+            None => 1,
         };
         let meta = GSMetaData {
             white_ks_castle,
@@ -260,6 +414,8 @@ impl ChessGame for GameState {
             white_qs_castle,
             black_qs_castle,
             enpasant_col,
+            halfmove,
+            fullmove,
         };
         Some(GameState { turn, board, meta })
     }
@@ -294,13 +450,24 @@ impl ChessGame for GameState {
                 // mov to 60 without moving from 60 would be taking the king
                 self.meta.black_ks_castle = false
             }
+            // only record en passant when an enemy pawn is beside the pawn
+            // that moved two, so it can actually be taken
             if self.board.pieces[fy][fx].unwrap().piece() == Piece::Pawn
                 && ((fy == 1 && ty == 3) || (fy == 6 && ty == 4))
+                && self.board.ep_capturable(fx, self.turn.invert())
             {
                 self.meta.enpasant_col = Some(fx as u8);
             } else {
                 self.meta.enpasant_col = None;
             }
+            if self.board.pieces[fy][fx].unwrap().piece() == Piece::Pawn || mov.take.is_some() {
+                self.meta.halfmove = 0;
+            } else {
+                self.meta.halfmove += 1;
+            }
+            if self.turn == Black {
+                self.meta.fullmove += 1;
+            }
             //unwrap should be fine as move should be from a piece
             if self.board.pieces[fy][fx].unwrap().piece() == Piece::King {
                 if fx == 4 && tx == 6 {
@@ -400,7 +567,7 @@ impl ChessGame for GameState {
         }
 
         // castling moves are always the last two to be added to move vector
-        let mut i = moves.len() - 1;
+        let mut i = moves.len().wrapping_sub(1);
         for _ in 1..=2 {
             if i >= moves.len() {
                 break;
@@ -433,7 +600,7 @@ impl ChessGame for GameState {
                     moves.remove(i);
                 }
             }
-            i -= 1;
+            i = i.wrapping_sub(1);
         }
         moves
     }
@@ -481,11 +648,11 @@ impl GameState {
             dist += 1
         }
         let mut dist = 1;
-        while self.optionaly_add(col, x, y, x - dist, y, moves) {
+        while self.optionaly_add(col, x, y, x.wrapping_sub(dist), y, moves) {
             dist += 1
         }
         let mut dist = 1;
-        while self.optionaly_add(col, x, y, x, y - dist, moves) {
+        while self.optionaly_add(col, x, y, x, y.wrapping_sub(dist), moves) {
             dist += 1
         }
     }
@@ -497,15 +664,15 @@ impl GameState {
             dist += 1
         }
         let mut dist = 1;
-        while self.optionaly_add(col, x, y, x - dist, y + dist, moves) {
+        while self.optionaly_add(col, x, y, x.wrapping_sub(dist), y + dist, moves) {
             dist += 1
         }
         let mut dist = 1;
-        while self.optionaly_add(col, x, y, x + dist, y - dist, moves) {
+        while self.optionaly_add(col, x, y, x + dist, y.wrapping_sub(dist), moves) {
             dist += 1
         }
         let mut dist = 1;
-        while self.optionaly_add(col, x, y, x - dist, y - dist, moves) {
+        while self.optionaly_add(col, x, y, x.wrapping_sub(dist), y.wrapping_sub(dist), moves) {
             dist += 1
         }
     }
@@ -526,9 +693,9 @@ impl GameState {
                     match piece.piece() {
                         Piece::Pawn => {
                             let can_prom = y == 6 && is_white || y == 1 && !is_white;
-                            let nxs: [usize; 2] = [x - 1, x + 1];
-                            let ny: usize = if is_white { y + 1 } else { y - 1 };
-                            let ny2: usize = if is_white { y + 2 } else { y - 2 };
+                            let nxs: [usize; 2] = [x.wrapping_sub(1), x + 1];
+                            let ny: usize = if is_white { y + 1 } else { y.wrapping_sub(1) };
+                            let ny2: usize = if is_white { y + 2 } else { y.wrapping_sub(2) };
                             let ystart: usize = if is_white { 1 } else { 6 };
                             let ypassant: usize = if is_white { 4 } else { 3 };
 
@@ -648,9 +815,30 @@ impl GameState {
                             for di in 1..=2 {
                                 let dj = 3 - di;
                                 self.optionaly_add(piece_col, x, y, x + di, y + dj, &mut moves);
-                                self.optionaly_add(piece_col, x, y, x - di, y + dj, &mut moves);
-                                self.optionaly_add(piece_col, x, y, x + di, y - dj, &mut moves);
-                                self.optionaly_add(piece_col, x, y, x - di, y - dj, &mut moves);
+                                self.optionaly_add(
+                                    piece_col,
+                                    x,
+                                    y,
+                                    x.wrapping_sub(di),
+                                    y + dj,
+                                    &mut moves,
+                                );
+                                self.optionaly_add(
+                                    piece_col,
+                                    x,
+                                    y,
+                                    x + di,
+                                    y.wrapping_sub(dj),
+                                    &mut moves,
+                                );
+                                self.optionaly_add(
+                                    piece_col,
+                                    x,
+                                    y,
+                                    x.wrapping_sub(di),
+                                    y.wrapping_sub(dj),
+                                    &mut moves,
+                                );
                             }
                         }
                         Piece::Bishop => {
@@ -666,12 +854,33 @@ impl GameState {
                         Piece::King => {
                             self.optionaly_add(piece_col, x, y, x + 1, y + 1, &mut moves);
                             self.optionaly_add(piece_col, x, y, x + 1, y, &mut moves);
-                            self.optionaly_add(piece_col, x, y, x + 1, y - 1, &mut moves);
+                            self.optionaly_add(
+                                piece_col,
+                                x,
+                                y,
+                                x + 1,
+                                y.wrapping_sub(1),
+                                &mut moves,
+                            );
                             self.optionaly_add(piece_col, x, y, x, y + 1, &mut moves);
-                            self.optionaly_add(piece_col, x, y, x, y - 1, &mut moves);
-                            self.optionaly_add(piece_col, x, y, x - 1, y + 1, &mut moves);
-                            self.optionaly_add(piece_col, x, y, x - 1, y, &mut moves);
-                            self.optionaly_add(piece_col, x, y, x - 1, y - 1, &mut moves);
+                            self.optionaly_add(piece_col, x, y, x, y.wrapping_sub(1), &mut moves);
+                            self.optionaly_add(
+                                piece_col,
+                                x,
+                                y,
+                                x.wrapping_sub(1),
+                                y + 1,
+                                &mut moves,
+                            );
+                            self.optionaly_add(piece_col, x, y, x.wrapping_sub(1), y, &mut moves);
+                            self.optionaly_add(
+                                piece_col,
+                                x,
+                                y,
+                                x.wrapping_sub(1),
+                                y.wrapping_sub(1),
+                                &mut moves,
+                            );
                         }
                     }
                 }
@@ -732,3 +941,186 @@ impl GameState {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{Board, GameState},
+        game::{ChessGame, Move},
+    };
+
+    #[test]
+    fn board_placement_sizes() {
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").is_some());
+        // nine squares on the second rank
+        assert!(Board::from_fen("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").is_none());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/44p/8/PPPPPPPP/RNBQKBNR").is_none());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR").is_none());
+        // seven squares on the second rank
+        assert!(Board::from_fen("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").is_none());
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP").is_none());
+        assert!(
+            GameState::from_fen("rnbqkbnr/pppppppp/8/8/8/7/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn file_letter_castling() {
+        let moves = |fen: &str| {
+            let mut moves = GameState::from_fen(fen).unwrap().legal_uci();
+            moves.sort();
+            moves
+        };
+        let standard = moves("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(standard.iter().any(|m| m == "e1c1"));
+        assert_eq!(moves("r3k2r/8/8/8/8/8/8/R3K2R w AHah - 0 1"), standard);
+        assert_eq!(
+            moves("r3k2r/8/8/8/8/8/8/R3K2R w Hh - 0 1"),
+            moves("r3k2r/8/8/8/8/8/8/R3K2R w Kk - 0 1")
+        );
+        assert!(GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w BHah - 0 1").is_none());
+    }
+
+    #[test]
+    fn short_fens() {
+        let full = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1";
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq",
+        ] {
+            assert_eq!(GameState::from_fen(fen).unwrap().to_fen(), full);
+        }
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w").is_none());
+    }
+
+    #[test]
+    fn dash_castling_field() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1";
+        let gs = GameState::from_fen(fen).unwrap();
+        let meta = &gs.meta;
+        assert!(
+            !(meta.white_ks_castle
+                || meta.white_qs_castle
+                || meta.black_ks_castle
+                || meta.black_qs_castle)
+        );
+        assert_eq!(gs.to_fen(), fen);
+        let moves = gs.legal_uci();
+        assert!(!moves.iter().any(|m| m == "e1g1" || m == "e1c1"));
+        assert_eq!(moves.len(), 24);
+        let black = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b - - 0 1").unwrap();
+        assert!(!black.legal_uci().iter().any(|m| m == "e8g8" || m == "e8c8"));
+    }
+
+    #[test]
+    fn king_counts() {
+        assert!(GameState::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").is_none());
+        assert!(GameState::from_fen("4k2P/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/p3K3 w - - 0 1").is_none());
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_some());
+    }
+
+    #[test]
+    fn en_passant_only_when_capturable() {
+        let mut gs = GameState::new();
+        let push = gs
+            .moves()
+            .into_iter()
+            .find(|m| m.to_uci() == "e2e4")
+            .unwrap();
+        gs.do_move(&push);
+        assert_eq!(gs.meta.enpasant_col, None);
+
+        let mut gs = GameState::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        let push = gs
+            .moves()
+            .into_iter()
+            .find(|m| m.to_uci() == "d7d5")
+            .unwrap();
+        let undo = gs.do_move(&push);
+        assert_eq!(gs.meta.enpasant_col, Some(3));
+        assert!(gs.moves().iter().any(|m| m.to_uci() == "e5d6"));
+        gs.unmove(&undo);
+        assert_eq!(gs.meta.enpasant_col, None);
+
+        // a phantom ep square is dropped, a badly placed one is rejected
+        let phantom = GameState::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(phantom.meta.enpasant_col, None);
+        let real = GameState::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(real.meta.enpasant_col, Some(3));
+        assert!(GameState::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 2").is_none());
+        assert!(GameState::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - x6 0 2").is_none());
+    }
+
+    #[test]
+    fn make_uci_line() {
+        let mut gs = GameState::new();
+        let mut undos = Vec::new();
+        for uci in ["e2e4", "c7c5", "g1f3", "d7d6", "d2d4", "c5d4", "f3d4"] {
+            undos.push(gs.make_uci(uci).unwrap());
+        }
+        println!("{gs}");
+        assert_eq!(
+            gs.to_fen(),
+            "rnbqkbnr/pp2pppp/3p4/8/3NP3/8/PPP2PPP/RNBQKB1R b KQkq - 0 4"
+        );
+        // illegal or malformed moves change nothing
+        assert!(gs.make_uci("e1e2").is_none());
+        assert!(gs.make_uci("e7e4").is_none());
+        assert!(gs.make_uci("junk").is_none());
+        assert_eq!(
+            gs.to_fen(),
+            "rnbqkbnr/pp2pppp/3p4/8/3NP3/8/PPP2PPP/RNBQKB1R b KQkq - 0 4"
+        );
+        while let Some(undo) = undos.pop() {
+            gs.unmove(&undo);
+        }
+        assert_eq!(gs.to_fen(), GameState::new().to_fen());
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+            "4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1",
+            "r3k3/8/8/8/8/8/8/4K2R b Kq - 37 60",
+        ] {
+            assert_eq!(GameState::from_fen(fen).unwrap().to_fen(), fen);
+        }
+        // missing counters default to the start of a game
+        assert_eq!(
+            GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - -")
+                .unwrap()
+                .to_fen(),
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1"
+        );
+        assert!(GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").is_none());
+
+        // the clock resets on pawn moves and captures, the move number goes
+        // up after black moves
+        let mut gs = GameState::new();
+        let mut undos = Vec::new();
+        for uci in ["g1f3", "b8c6", "f3e5", "c6e5", "e2e4"] {
+            let mov = gs.moves().into_iter().find(|m| m.to_uci() == uci).unwrap();
+            undos.push(gs.do_move(&mov));
+        }
+        assert_eq!(
+            gs.to_fen(),
+            "r1bqkbnr/pppppppp/8/4n3/4P3/8/PPPP1PPP/RNBQKB1R b KQkq - 0 3"
+        );
+        gs.unmove(&undos.pop().unwrap());
+        assert_eq!(
+            gs.to_fen(),
+            "r1bqkbnr/pppppppp/8/4n3/8/8/PPPPPPPP/RNBQKB1R w KQkq - 0 3"
+        );
+        gs.unmove(&undos.pop().unwrap());
+        assert_eq!(
+            gs.to_fen(),
+            "r1bqkbnr/pppppppp/2n5/4N3/8/8/PPPPPPPP/RNBQKB1R b KQkq - 3 2"
+        );
+    }
+}
@@ -1,9 +1,36 @@
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::io::{self, BufRead};
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
-pub struct GoArgs<'a> {
-    pub moves: Option<Vec<&'a str>>,
+use crate::{
+    bit_board::{BitBoardGame, BitBoardGameMove},
+    eval::TaperedEvaluator,
+    game::{ChessGame, Move},
+    piece::PlayerColour,
+    search::{
+        self, is_mate_score, iterative_deepening_with_state, multi_pv_with_state, PvLine,
+        SearchState,
+    },
+};
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The arguments of a UCI `go` command. `do_uci` parses them, library users
+/// can start from `GoArgs::default()`, which searches without limits, and
+/// set fields with the `with_` methods. It owns its moves so it can be kept
+/// or sent to another thread once the command line is gone
+#[derive(Default, Clone, Debug)]
+pub struct GoArgs {
+    pub moves: Option<Vec<String>>,
     pub ponder: bool,
     pub wtime: Option<u64>,
     pub btime: Option<u64>,
@@ -17,6 +44,74 @@ pub struct GoArgs<'a> {
     pub infinite: bool,
 }
 
+impl GoArgs {
+    /// only search these moves
+    pub fn with_moves<S: Into<String>>(mut self, moves: impl IntoIterator<Item = S>) -> Self {
+        self.moves = Some(moves.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_ponder(mut self, ponder: bool) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
+    /// white's clock in milliseconds
+    pub fn with_wtime(mut self, wtime: u64) -> Self {
+        self.wtime = Some(wtime);
+        self
+    }
+
+    /// black's clock in milliseconds
+    pub fn with_btime(mut self, btime: u64) -> Self {
+        self.btime = Some(btime);
+        self
+    }
+
+    /// white's increment in milliseconds
+    pub fn with_winc(mut self, winc: u64) -> Self {
+        self.winc = Some(winc);
+        self
+    }
+
+    /// black's increment in milliseconds
+    pub fn with_binc(mut self, binc: u64) -> Self {
+        self.binc = Some(binc);
+        self
+    }
+
+    pub fn with_movestogo(mut self, movestogo: u64) -> Self {
+        self.movestogo = Some(movestogo);
+        self
+    }
+
+    pub fn with_depth(mut self, depth: u64) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn with_mate(mut self, mate: u64) -> Self {
+        self.mate = Some(mate);
+        self
+    }
+
+    /// exact time for the move in milliseconds
+    pub fn with_movetime(mut self, move_time: u64) -> Self {
+        self.move_time = Some(move_time);
+        self
+    }
+
+    pub fn with_infinite(mut self, infinite: bool) -> Self {
+        self.infinite = infinite;
+        self
+    }
+}
+
 pub trait Engine {
     fn new() -> Self;
     fn set_from_fen(&mut self, fen: &str);
@@ -26,11 +121,25 @@ pub trait Engine {
     fn get_author(&self) -> String;
     fn set_debug(&self, b: bool);
     fn log(&self, log: &str);
-    fn go<'a>(&self, args: &'a GoArgs);
+    /// start a search, `do_uci` reads no more commands until this returns
+    /// so a search that can run forever should go on another thread
+    fn go(&self, args: &GoArgs);
+    /// the opponent played the move a `go ponder` search expected, carry on
+    /// as a normal search
+    fn ponder_hit(&self);
     fn stop(&self);
+    /// `option` lines printed in reply to `uci`
+    fn options(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// `setoption name <name> value <value>`, `value` is empty if not given
+    fn set_option(&mut self, name: &str, value: &str) {
+        self.log(&format!("unknown option {name} = {value}"));
+    }
 }
 
 lazy_static! {
+    static ref START_POS_960: Regex = Regex::new(r"^ ?startpos960 ([0-9]+)( moves(( [a-h][1-8][a-h][1-8][rnbq]?)+))?").unwrap();
     static ref START_POS: Regex = Regex::new(r"^ ?startpos( moves(( [a-h][1-8][a-h][1-8][rnbq]?)+))?").unwrap();
     static ref FEN_POS: Regex = Regex::new(r"^ ?([pnbrqkPNBRQK1-8/]* [bw] ((K?Q?k?q?)|\-) (([a-h][1-8])|\-)( (([1-9][0-9]*)|\-|0))?( (([1-9][0-9]*)|\-|0))?)( moves(( [a-h][1-8][a-h][1-8][rnbq]?)+))?").unwrap();
     static ref SEARCH_MOVES: Regex = Regex::new(r"searchmoves(( [a-h][1-8][a-h][1-8][rnbq]?)+)").unwrap();
@@ -45,48 +154,87 @@ lazy_static! {
     static ref MATE: Regex = Regex::new(r"mate (([1-9][0-9]*)|0)").unwrap();
     static ref MOVE_TIME: Regex = Regex::new(r"movetime (([1-9][0-9]*)|0)").unwrap();
     static ref INFINITE: Regex = Regex::new(r"infinite").unwrap();
+    static ref SET_OPTION: Regex = Regex::new(r"^name (.+?)(?: value (.*))?$").unwrap();
 }
 
 pub fn do_uci<Eng: Engine>(eng: &mut Eng) {
-    let stdin = io::stdin();
-    let mut iterator = stdin.lock().lines();
+    do_uci_with(eng, io::stdin().lock());
+}
+
+/// `do_uci` reading commands from `input`, the end of the input is treated
+/// as `quit`
+pub fn do_uci_with<Eng: Engine, R: BufRead>(eng: &mut Eng, input: R) {
+    let mut iterator = input.lines();
 
     loop {
-        let input = iterator.next().unwrap().unwrap() + " ";
-        let command = input.trim().split_once(" ");
+        let Some(Ok(line)) = iterator.next() else {
+            eng.stop();
+            return;
+        };
+        let input = line.trim();
+        let command = input.split_once(' ').unwrap_or((input, ""));
         match command {
-            Some(("uci", _)) => {
+            ("uci", _) => {
                 println!("id name {}", eng.get_name());
                 println!("id author {}", eng.get_author());
+                for option in eng.options() {
+                    println!("{option}");
+                }
                 println!("uciok");
             }
-            Some(("debug", "on")) => {
+            ("debug", "on") => {
                 eng.set_debug(true);
             }
-            Some(("debug", "off")) => {
+            ("debug", "off") => {
                 eng.set_debug(false);
             }
-            Some(("isready", _)) => {
+            ("isready", _) => {
                 println!("readyok");
             }
-            Some(("setoption", rest)) => {
-                eng.log(&format!("tried to set option {rest}"));
-            }
-            Some(("register", rest)) => {
+            ("setoption", rest) => match SET_OPTION.captures(rest.trim()) {
+                Some(m) => {
+                    let value = m.get(2).map_or("", |value| value.as_str().trim());
+                    eng.set_option(m[1].trim(), value);
+                }
+                None => eng.log(&format!("tried to set option {rest}")),
+            },
+            ("register", rest) => {
                 todo!("tried to register {rest}");
             }
-            Some(("ucinewgame", rest)) => {
+            ("ucinewgame", rest) => {
                 eng.log(&format!("starting new game: {rest}"));
             }
-            Some(("position", rest)) => {
-                let sp = match START_POS.captures(rest) {
-                    Some(m) => m.get(2),
-                    None => match FEN_POS.captures(rest) {
-                        Some(m) => m.get(13),
-                        None => {
-                            eng.log(&format!("sp no match {rest}"));
-                            continue;
+            ("position", rest) => {
+                // Chess960 start positions by Scharnagl number, checked first
+                // as `startpos` would match their start
+                let start_960 = START_POS_960.captures(rest).map(|m| {
+                    let game = m[1].parse().ok().and_then(BitBoardGame::from_960_id);
+                    (game, m.get(3))
+                });
+                let sp = match start_960 {
+                    Some((Some(game), moves)) => {
+                        eng.set_from_fen(&game.to_fen());
+                        moves
+                    }
+                    Some((None, _)) => {
+                        eng.log(&format!("bad chess960 id {rest}"));
+                        continue;
+                    }
+                    None => match START_POS.captures(rest) {
+                        Some(m) => {
+                            eng.set_from_fen(START_FEN);
+                            m.get(2)
                         }
+                        None => match FEN_POS.captures(rest) {
+                            Some(m) => {
+                                eng.set_from_fen(m.get(1).unwrap().as_str());
+                                m.get(13)
+                            }
+                            None => {
+                                eng.log(&format!("sp no match {rest}"));
+                                continue;
+                            }
+                        },
                     },
                 };
                 match sp {
@@ -100,13 +248,14 @@ pub fn do_uci<Eng: Engine>(eng: &mut Eng) {
                     None => eng.log("no moves"),
                 }
             }
-            Some(("go", rest)) => {
+            ("go", rest) => {
                 let moves = SEARCH_MOVES.captures(rest).map(|m| {
                     m.get(1)
                         .unwrap()
                         .as_str()
                         .trim()
                         .split_whitespace()
+                        .map(str::to_string)
                         .collect()
                 });
                 let ponder = PONDER.is_match(rest);
@@ -162,15 +311,714 @@ pub fn do_uci<Eng: Engine>(eng: &mut Eng) {
                 };
                 eng.go(&garg);
             }
-            Some(("stop", _)) => eng.stop(),
-            Some(("quit", _)) => {
+            ("ponderhit", _) => eng.ponder_hit(),
+            ("stop", _) => eng.stop(),
+            ("quit", _) => {
                 eng.stop();
                 return;
             }
-            Some((t, _)) => {
+            (t, _) => {
                 eng.log(&format!("unknown command \"{t}\""));
             }
-            None => todo!(),
         }
     }
 }
+
+/// search depth when `go` doesn't give one
+const DEFAULT_DEPTH: u8 = 5;
+/// size of the transposition table for each search
+const TABLE_BITS: u8 = 20;
+/// most lines the `MultiPV` option can ask for
+const MAX_MULTI_PV: usize = 256;
+/// moves the clock is shared over when `go` doesn't give `movestogo`
+const SUDDEN_DEATH_MOVES: u64 = 30;
+/// milliseconds kept back from every move for the time lost passing
+/// commands between the gui and engine
+const MOVE_OVERHEAD: u64 = 50;
+
+/// whether `args` gives a clock or move time for the side to move, which
+/// a ponder search only starts keeping to at `ponderhit`
+fn is_timed(args: &GoArgs, white_to_move: bool) -> bool {
+    let clock = if white_to_move {
+        args.wtime
+    } else {
+        args.btime
+    };
+    !args.infinite && (args.move_time.is_some() || clock.is_some())
+}
+
+/// Time to spend on the next move. `movetime` is used as given, otherwise
+/// the side's clock is shared evenly over `movestogo` moves (or
+/// `SUDDEN_DEATH_MOVES`) and the increment added, never using more than the
+/// clock less `MOVE_OVERHEAD`. A missing clock counts as no time left
+pub fn allocate_time(args: &GoArgs, white_to_move: bool) -> Duration {
+    if let Some(move_time) = args.move_time {
+        return Duration::from_millis(move_time);
+    }
+    let (time, inc) = if white_to_move {
+        (args.wtime, args.winc)
+    } else {
+        (args.btime, args.binc)
+    };
+    let time = time.unwrap_or(0);
+    let moves = args
+        .movestogo
+        .filter(|&moves| moves > 0)
+        .unwrap_or(SUDDEN_DEATH_MOVES);
+    let budget = time / moves + inc.unwrap_or(0);
+    Duration::from_millis(budget.min(time.saturating_sub(MOVE_OVERHEAD)))
+}
+
+/// Engine choosing moves by `iterative_deepening` with the
+/// `TaperedEvaluator`. `go` starts the search on a thread of its own and
+/// returns, the search runs until its depth, the time from `allocate_time`
+/// runs out or `stop` sets the flag from `stop_flag`, then prints its
+/// `bestmove`. `select_move` waits for the running search to finish.
+/// A timed search without a depth goes as deep as the time allows.
+/// A `go ponder` search runs on the position with the expected reply
+/// already played, keeps to no clock until `ponderhit` starts its time from
+/// `allocate_time` and holds back its `bestmove` until `ponderhit` or
+/// `stop`. With the `MultiPV` option above 1 every root move is searched with
+/// a full window at each depth and the best lines are sent as
+/// `info multipv` lines.
+pub struct FesEngine {
+    game: BitBoardGame,
+    /// result of the last search, shared with the thread running it
+    outcome: Arc<Mutex<Outcome>>,
+    /// the thread running the last search until it is joined
+    worker: RefCell<Option<JoinHandle<()>>>,
+    stop: Arc<AtomicBool>,
+    /// when the running search stops itself, shared with its thread
+    deadline: Arc<Mutex<Option<Instant>>>,
+    /// the time a ponder search with a clock gets once `ponderhit` comes
+    ponder_time: Cell<Option<Duration>>,
+    debug: Cell<bool>,
+    /// lines reported by each search, the `MultiPV` option
+    multi_pv: usize,
+}
+
+impl FesEngine {
+    pub fn game(&self) -> &BitBoardGame {
+        &self.game
+    }
+
+    /// Setting this flag from another thread ends the running search, which
+    /// then reports the best move found so far
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// true between a `go ponder` and the `ponderhit` or `stop` ending it
+    pub fn is_pondering(&self) -> bool {
+        self.outcome.lock().unwrap().pondering
+    }
+
+    /// wait for the thread running the last search
+    fn join(&self) {
+        if let Some(worker) = self.worker.borrow_mut().take() {
+            worker.join().unwrap();
+        }
+    }
+
+    /// the move of a ponder search is reported once it has finished and
+    /// the `ponderhit` or `stop` ending it has come
+    fn end_ponder(&self) {
+        let mut outcome = self.outcome.lock().unwrap();
+        if outcome.pondering {
+            outcome.pondering = false;
+            if outcome.done {
+                report(outcome.best);
+            }
+        }
+    }
+}
+
+/// what the thread running a search hands back to the engine
+#[derive(Default)]
+struct Outcome {
+    /// packed best move, 0 if the search found none
+    best: u16,
+    /// the search has finished
+    done: bool,
+    /// a ponder search holding back its move
+    pondering: bool,
+}
+
+fn report(best: u16) {
+    match best {
+        0 => println!("bestmove 0000"),
+        best => println!("bestmove {}", best.to_uci()),
+    }
+}
+
+/// A search of its own copy of the engine's position, so it can run on
+/// another thread while the engine reads commands
+struct Search {
+    game: BitBoardGame,
+    multi_pv: usize,
+    stop: Arc<AtomicBool>,
+    deadline: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Search {
+    /// The best move for `args`, None if the game is over or every
+    /// `searchmoves` move is illegal
+    fn run(&self, args: &GoArgs) -> Option<BitBoardGameMove> {
+        let mut state = SearchState::new(TABLE_BITS);
+        state.stop = self.stop.clone();
+        state.deadline = self.deadline.clone();
+        if let Some(moves) = &args.moves {
+            state.root_moves = moves
+                .iter()
+                .filter_map(|uci| self.game.move_from_uci(uci))
+                .map(|mov| mov.mov)
+                .collect();
+            if state.root_moves.is_empty() {
+                return None;
+            }
+        }
+        let white = self.game.turn() == PlayerColour::White;
+        let depth = match args.depth {
+            _ if args.infinite => u8::MAX,
+            Some(depth) => depth.clamp(1, u8::MAX.into()) as u8,
+            None if is_timed(args, white) => u8::MAX,
+            None => DEFAULT_DEPTH,
+        };
+        if self.multi_pv > 1 {
+            return self.search_multi_pv(&mut state, depth);
+        }
+        iterative_deepening_with_state(&self.game, &TaperedEvaluator, &mut state, depth)
+            .map(|(mov, _)| mov)
+    }
+
+    /// `multi_pv_with_state` at depths 1 to `max_depth` in turn, printing
+    /// the lines of each completed depth and stopping early once the best
+    /// line is a mate. The best move of the last completed depth is
+    /// returned, or of the partial first depth if none completed
+    fn search_multi_pv(&self, state: &mut SearchState, max_depth: u8) -> Option<BitBoardGameMove> {
+        let mut best = None;
+        for depth in 1..=max_depth {
+            let lines =
+                multi_pv_with_state(&self.game, &TaperedEvaluator, state, depth, self.multi_pv);
+            if state.stopped() {
+                return best.or_else(|| lines.into_iter().next().map(|line| line.mov));
+            }
+            for (rank, line) in lines.iter().enumerate() {
+                println!("{}", multi_pv_info(depth, rank + 1, line));
+            }
+            let first = lines.into_iter().next()?;
+            let mate = is_mate_score(first.score);
+            best = Some(first.mov);
+            if mate {
+                break;
+            }
+        }
+        best
+    }
+}
+
+impl Drop for FesEngine {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.join();
+    }
+}
+
+/// A score for the side to move as UCI `cp <centipawns>` or `mate <moves>`,
+/// negative mates being the side to move getting mated
+fn uci_score(score: i32) -> String {
+    if !is_mate_score(score) {
+        format!("cp {score}")
+    } else if score > 0 {
+        format!("mate {}", (search::MATE - score + 1) / 2)
+    } else {
+        format!("mate -{}", (search::MATE + score) / 2)
+    }
+}
+
+/// the `info` line reporting `line` as the `rank`th best at `depth`
+fn multi_pv_info(depth: u8, rank: usize, line: &PvLine) -> String {
+    let pv: Vec<_> = line.pv.iter().map(|mov| mov.to_uci()).collect();
+    format!(
+        "info depth {depth} multipv {rank} score {} pv {}",
+        uci_score(line.score),
+        pv.join(" ")
+    )
+}
+
+impl Engine for FesEngine {
+    fn new() -> Self {
+        Self {
+            game: BitBoardGame::new(),
+            outcome: Arc::default(),
+            worker: RefCell::new(None),
+            stop: Arc::new(AtomicBool::new(false)),
+            deadline: Arc::default(),
+            ponder_time: Cell::new(None),
+            debug: Cell::new(false),
+            multi_pv: 1,
+        }
+    }
+
+    fn set_from_fen(&mut self, fen: &str) {
+        match BitBoardGame::from_fen(fen) {
+            Some(game) => self.game = game,
+            None => self.log(&format!("bad fen {fen}")),
+        }
+    }
+
+    fn play_move(&mut self, mov: &str) {
+        if self.game.play_uci(&[mov]).is_err() {
+            self.log(&format!("illegal move {mov}"));
+        }
+    }
+
+    fn select_move(&self) -> u16 {
+        self.join();
+        self.outcome.lock().unwrap().best
+    }
+
+    fn get_name(&self) -> String {
+        "FeS".to_string()
+    }
+
+    fn get_author(&self) -> String {
+        "NailLegProcessorDivide".to_string()
+    }
+
+    fn set_debug(&self, b: bool) {
+        self.debug.set(b);
+    }
+
+    fn log(&self, log: &str) {
+        if self.debug.get() {
+            println!("info string {log}");
+        }
+    }
+
+    fn go(&self, args: &GoArgs) {
+        // a search still running is abandoned, holding back its move like a
+        // ponder search's
+        self.outcome.lock().unwrap().pondering = true;
+        self.stop.store(true, Ordering::Relaxed);
+        self.join();
+        self.stop.store(false, Ordering::Relaxed);
+        *self.outcome.lock().unwrap() = Outcome {
+            pondering: args.ponder,
+            ..Outcome::default()
+        };
+        let white = self.game.turn() == PlayerColour::White;
+        let time = is_timed(args, white).then(|| allocate_time(args, white));
+        let (time, ponder_time) = if args.ponder {
+            (None, time)
+        } else {
+            (time, None)
+        };
+        *self.deadline.lock().unwrap() = time.map(|time| Instant::now() + time);
+        self.ponder_time.set(ponder_time);
+        let search = Search {
+            game: self.game.clone(),
+            multi_pv: self.multi_pv,
+            stop: self.stop.clone(),
+            deadline: self.deadline.clone(),
+        };
+        let outcome = self.outcome.clone();
+        let args = args.clone();
+        let worker = thread::spawn(move || {
+            let best = search.run(&args).map_or(0, |mov| mov.mov);
+            let mut outcome = outcome.lock().unwrap();
+            outcome.best = best;
+            outcome.done = true;
+            if !outcome.pondering {
+                report(best);
+            }
+        });
+        *self.worker.borrow_mut() = Some(worker);
+    }
+
+    fn ponder_hit(&self) {
+        if let Some(time) = self.ponder_time.take() {
+            *self.deadline.lock().unwrap() = Some(Instant::now() + time);
+        }
+        self.end_ponder();
+    }
+
+    fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.end_ponder();
+    }
+
+    fn options(&self) -> Vec<String> {
+        vec![format!(
+            "option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}"
+        )]
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        match value.parse::<usize>() {
+            Ok(lines) if name.eq_ignore_ascii_case("MultiPV") => {
+                self.multi_pv = lines.clamp(1, MAX_MULTI_PV);
+            }
+            _ => self.log(&format!("unknown option {name} = {value}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        sync::atomic::Ordering,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        bit_board::BitBoardGame,
+        engine::{allocate_time, do_uci_with, multi_pv_info, uci_score, Engine, FesEngine, GoArgs},
+        eval::TaperedEvaluator,
+        game::{ChessGame, Move},
+        search::{multi_pv_with_state, SearchState, MATE},
+    };
+
+    /// records the calls `do_uci` makes
+    struct ScriptEngine {
+        calls: RefCell<Vec<String>>,
+        go_args: RefCell<Vec<GoArgs>>,
+    }
+
+    impl ScriptEngine {
+        fn record(&self, call: String) {
+            self.calls.borrow_mut().push(call);
+        }
+    }
+
+    impl Engine for ScriptEngine {
+        fn new() -> Self {
+            Self {
+                calls: RefCell::new(Vec::new()),
+                go_args: RefCell::new(Vec::new()),
+            }
+        }
+
+        fn set_from_fen(&mut self, fen: &str) {
+            self.record(format!("fen {fen}"));
+        }
+
+        fn play_move(&mut self, mov: &str) {
+            self.record(format!("move {mov}"));
+        }
+
+        fn select_move(&self) -> u16 {
+            0
+        }
+
+        fn get_name(&self) -> String {
+            "script".to_string()
+        }
+
+        fn get_author(&self) -> String {
+            "script".to_string()
+        }
+
+        fn set_debug(&self, _b: bool) {}
+
+        fn log(&self, _log: &str) {}
+
+        fn go(&self, args: &GoArgs) {
+            self.record(format!("go ponder={} depth={:?}", args.ponder, args.depth));
+            self.go_args.borrow_mut().push(args.clone());
+        }
+
+        fn ponder_hit(&self) {
+            self.record("ponderhit".to_string());
+        }
+
+        fn stop(&self) {
+            self.record("stop".to_string());
+        }
+
+        fn set_option(&mut self, name: &str, value: &str) {
+            self.record(format!("option {name}={value}"));
+        }
+    }
+
+    fn go_args(moves: Option<Vec<&str>>, depth: u64) -> GoArgs {
+        let args = GoArgs::default().with_depth(depth);
+        match moves {
+            Some(moves) => args.with_moves(moves),
+            None => args,
+        }
+    }
+
+    #[test]
+    fn go_args_builder() {
+        let args = GoArgs::default();
+        assert!(args.moves.is_none() && args.depth.is_none() && args.move_time.is_none());
+        assert!(!args.ponder && !args.infinite);
+
+        let args = GoArgs::default()
+            .with_moves(vec!["e2e4", "d2d4"])
+            .with_wtime(60_000)
+            .with_btime(50_000)
+            .with_winc(1_000)
+            .with_binc(2_000)
+            .with_movestogo(20)
+            .with_nodes(10_000)
+            .with_mate(3)
+            .with_movetime(500)
+            .with_ponder(true)
+            .with_infinite(true)
+            .with_depth(4);
+        assert_eq!(args.moves.clone().unwrap(), ["e2e4", "d2d4"]);
+        assert_eq!(
+            (args.wtime, args.btime, args.winc, args.binc),
+            (Some(60_000), Some(50_000), Some(1_000), Some(2_000))
+        );
+        assert_eq!(
+            (args.movestogo, args.nodes, args.mate, args.move_time),
+            (Some(20), Some(10_000), Some(3), Some(500))
+        );
+        assert!(args.ponder && args.infinite);
+
+        let eng = ScriptEngine::new();
+        eng.go(&args);
+        eng.go(&GoArgs::default().with_depth(2));
+        assert_eq!(
+            eng.calls.into_inner(),
+            [
+                "go ponder=true depth=Some(4)",
+                "go ponder=false depth=Some(2)"
+            ]
+        );
+
+        let eng = FesEngine::new();
+        eng.go(&GoArgs::default().with_depth(2).with_moves(vec!["g1f3"]));
+        assert_eq!(eng.select_move().to_uci(), "g1f3");
+    }
+
+    #[test]
+    fn search_moves() {
+        let mut eng = FesEngine::new();
+        eng.go(&go_args(Some(vec!["e2e4"]), 3));
+        assert_eq!(eng.select_move().to_uci(), "e2e4");
+
+        // d1d3 hangs the queen
+        eng.set_from_fen("4k3/8/8/3q4/8/8/8/1N1QK3 w - - 0 1");
+        eng.go(&go_args(Some(vec!["d1d3", "b1c3"]), 2));
+        assert_eq!(eng.select_move().to_uci(), "b1c3");
+        eng.go(&go_args(None, 2));
+        assert_eq!(eng.select_move().to_uci(), "d1d5");
+
+        // nothing legal to search
+        eng.go(&go_args(Some(vec!["e2e4", "d1d8"]), 2));
+        assert_eq!(eng.select_move(), 0);
+
+        // illegal moves are ignored
+        eng.play_move("d1d5");
+        eng.play_move("e8d8");
+        eng.play_move("e8e7");
+        assert_eq!(eng.game().to_fen(), "8/4k3/8/3Q4/8/8/8/1N2K3 w - - 1 2");
+    }
+
+    #[test]
+    fn go_args_outlive_line() {
+        let script = "position startpos\ngo searchmoves d2d4 g1f3 depth 2\nquit\n";
+        let mut eng = ScriptEngine::new();
+        do_uci_with(&mut eng, script.as_bytes());
+        let args = eng.go_args.into_inner().pop().unwrap();
+        assert_eq!(args.moves.clone().unwrap(), ["d2d4", "g1f3"]);
+        // the input is gone, the arguments can still start a search elsewhere
+        let search = thread::spawn(move || {
+            let eng = FesEngine::new();
+            eng.go(&args);
+            eng.select_move().to_uci()
+        });
+        assert!(["d2d4", "g1f3"].contains(&search.join().unwrap().as_str()));
+    }
+
+    #[test]
+    fn multi_pv_option() {
+        let script = "setoption name MultiPV value 2\nsetoption name Clear Hash\nquit\n";
+        let mut eng = ScriptEngine::new();
+        do_uci_with(&mut eng, script.as_bytes());
+        assert_eq!(
+            eng.calls.into_inner(),
+            ["option MultiPV=2", "option Clear Hash=", "stop"]
+        );
+
+        let gs = BitBoardGame::from_fen("4k3/8/8/3q4/8/8/8/1N1QK3 w - - 0 1").unwrap();
+        let lines = multi_pv_with_state(&gs, &TaperedEvaluator, &mut SearchState::new(16), 2, 2);
+        let info: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(rank, line)| multi_pv_info(2, rank + 1, line))
+            .collect();
+        assert!(info[0].starts_with("info depth 2 multipv 1 score cp "));
+        assert!(info[0].ends_with(" pv d1d5 e8e7"));
+        assert!(info[1].starts_with("info depth 2 multipv 2 score cp "));
+        assert_ne!(
+            info[0]["info depth 2 multipv 1 ".len()..],
+            info[1]["info depth 2 multipv 2 ".len()..]
+        );
+
+        assert_eq!(uci_score(-35), "cp -35");
+        assert_eq!(uci_score(MATE - 1), "mate 1");
+        assert_eq!(uci_score(MATE - 5), "mate 3");
+        assert_eq!(uci_score(2 - MATE), "mate -1");
+
+        let mut eng = FesEngine::new();
+        do_uci_with(&mut eng, "setoption name multipv value 2\n".as_bytes());
+        eng.set_from_fen("4k3/8/8/3q4/8/8/8/1N1QK3 w - - 0 1");
+        eng.go(&go_args(None, 2));
+        assert_eq!(eng.select_move().to_uci(), "d1d5");
+        assert_eq!(eng.options().len(), 1);
+    }
+
+    #[test]
+    fn chess960_startpos() {
+        let script = "position startpos960 518 moves e2e4\nposition startpos960 960\nquit\n";
+        let mut eng = ScriptEngine::new();
+        do_uci_with(&mut eng, script.as_bytes());
+        assert_eq!(
+            eng.calls.into_inner(),
+            [
+                "fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1",
+                "move e2e4",
+                "stop",
+            ]
+        );
+
+        let mut eng = FesEngine::new();
+        do_uci_with(&mut eng, "position startpos960 0\n".as_bytes());
+        assert_eq!(
+            eng.game().to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+    }
+
+    #[test]
+    fn ponder_script() {
+        let script = "position startpos moves e2e4 e7e5\ngo ponder depth 2\nponderhit\nquit\n";
+        let mut eng = ScriptEngine::new();
+        do_uci_with(&mut eng, script.as_bytes());
+        assert_eq!(
+            eng.calls.into_inner(),
+            [
+                "fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "move e2e4",
+                "move e7e5",
+                "go ponder=true depth=Some(2)",
+                "ponderhit",
+                "stop",
+            ]
+        );
+
+        let mut eng = FesEngine::new();
+        eng.play_move("e2e4");
+        let mut args = go_args(None, 2);
+        args.ponder = true;
+        eng.go(&args);
+        assert!(eng.is_pondering());
+        assert!(eng
+            .game()
+            .move_from_uci(&eng.select_move().to_uci())
+            .is_some());
+        eng.ponder_hit();
+        assert!(!eng.is_pondering());
+
+        // stop also ends a ponder search
+        eng.go(&args);
+        eng.stop();
+        assert!(!eng.is_pondering());
+        eng.go(&go_args(None, 2));
+        assert!(!eng.is_pondering());
+
+        // a ponder search with a clock runs until ponderhit starts it
+        let eng = FesEngine::new();
+        let start = Instant::now();
+        eng.go(&GoArgs::default().with_ponder(true).with_wtime(3_000));
+        thread::sleep(Duration::from_millis(300));
+        assert!(!eng.worker.borrow().as_ref().unwrap().is_finished());
+        eng.ponder_hit();
+        assert!(eng
+            .game()
+            .move_from_uci(&eng.select_move().to_uci())
+            .is_some());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn stop_infinite_search() {
+        let mut eng = FesEngine::new();
+        let start = Instant::now();
+        do_uci_with(
+            &mut eng,
+            "position startpos\ngo infinite\nstop\nquit\n".as_bytes(),
+        );
+        assert!(eng
+            .game()
+            .move_from_uci(&eng.select_move().to_uci())
+            .is_some());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // the flag ends it from another thread too
+        let eng = FesEngine::new();
+        let stop = eng.stop_flag();
+        eng.go(&GoArgs::default().with_infinite(true));
+        thread::sleep(Duration::from_millis(200));
+        stop.store(true, Ordering::Relaxed);
+        assert!(eng
+            .game()
+            .move_from_uci(&eng.select_move().to_uci())
+            .is_some());
+
+        // a ponder search ended by stop
+        let mut eng = FesEngine::new();
+        do_uci_with(&mut eng, "position startpos\ngo ponder\nstop\n".as_bytes());
+        assert!(!eng.is_pondering());
+        assert!(eng
+            .game()
+            .move_from_uci(&eng.select_move().to_uci())
+            .is_some());
+    }
+
+    #[test]
+    fn time_allocation() {
+        // sudden death shares the clock over 30 moves plus the increment
+        let mut args = go_args(None, 1);
+        args.depth = None;
+        args.wtime = Some(60_000);
+        args.btime = Some(30_000);
+        args.winc = Some(1_000);
+        assert_eq!(allocate_time(&args, true), Duration::from_millis(3_000));
+        assert_eq!(allocate_time(&args, false), Duration::from_millis(1_000));
+
+        // fixed moves shares it over the moves left to the time control
+        args.movestogo = Some(10);
+        assert_eq!(allocate_time(&args, true), Duration::from_millis(7_000));
+        assert_eq!(allocate_time(&args, false), Duration::from_millis(3_000));
+        // the last move before the control keeps a margin
+        args.movestogo = Some(1);
+        assert_eq!(allocate_time(&args, false), Duration::from_millis(29_950));
+        args.btime = Some(20);
+        assert_eq!(allocate_time(&args, false), Duration::ZERO);
+
+        args.move_time = Some(500);
+        assert_eq!(allocate_time(&args, true), Duration::from_millis(500));
+
+        // a timed search without a depth stops on its own
+        let eng = FesEngine::new();
+        args.move_time = None;
+        args.movestogo = None;
+        args.wtime = Some(3_000);
+        args.winc = None;
+        let start = Instant::now();
+        eng.go(&args);
+        assert!(eng
+            .game()
+            .move_from_uci(&eng.select_move().to_uci())
+            .is_some());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::{board::GameState, game::ChessGame};
+
+/// An Extended Position Description record, the four position fields of a
+/// FEN followed by `opcode operand...;` operations
+#[derive(Debug, PartialEq, Clone)]
+pub struct Epd {
+    /// the position as a full FEN, the move clocks come from the `hmvc` and
+    /// `fmvn` operations or default to `0 1`
+    pub fen: String,
+    /// operands of each operation with string quotes removed
+    pub operations: HashMap<String, Vec<String>>,
+}
+
+impl Epd {
+    pub fn get(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.get(opcode).map(Vec::as_slice)
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.get("id")?.first().map(String::as_str)
+    }
+
+    /// SAN best moves, empty if there is no `bm`
+    pub fn best_moves(&self) -> &[String] {
+        self.get("bm").unwrap_or(&[])
+    }
+
+    /// SAN moves to avoid, empty if there is no `am`
+    pub fn avoid_moves(&self) -> &[String] {
+        self.get("am").unwrap_or(&[])
+    }
+
+    /// (depth, node count) from each `Dn` operation in depth order
+    pub fn perft_counts(&self) -> Vec<(usize, usize)> {
+        let mut counts: Vec<(usize, usize)> = self
+            .operations
+            .iter()
+            .filter_map(|(opcode, operands)| {
+                let depth = opcode.strip_prefix('D')?.parse().ok()?;
+                Some((depth, operands.first()?.parse().ok()?))
+            })
+            .collect();
+        counts.sort();
+        counts
+    }
+}
+
+/// split operations on `;` and operands on spaces, except inside quotes
+fn split_operations(ops: &str) -> Option<Vec<Vec<String>>> {
+    let mut operations = Vec::new();
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in ops.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                if !tokens.is_empty() {
+                    operations.push(std::mem::take(&mut tokens));
+                }
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    // an unterminated quote or a trailing operation without its `;`
+    if quoted || !token.is_empty() || !tokens.is_empty() {
+        return None;
+    }
+    Some(operations)
+}
+
+/// Parse one EPD line, None if the position is not a legal setup or the
+/// operations are malformed
+pub fn parse_epd(line: &str) -> Option<Epd> {
+    let line = line.trim();
+    let mut position = Vec::with_capacity(4);
+    let mut rest = line;
+    for _ in 0..4 {
+        let (field, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        if field.is_empty() {
+            return None;
+        }
+        position.push(field);
+        rest = tail.trim_start();
+    }
+    let position = position.join(" ");
+    GameState::from_fen(&position)?;
+
+    let mut operations = HashMap::new();
+    for mut operation in split_operations(rest)? {
+        let opcode = operation.remove(0);
+        operations.insert(opcode, operation);
+    }
+    let clock = |opcode: &str, default: u16| match operations.get(opcode) {
+        Some(operands) => operands.first()?.parse::<u16>().ok(),
+        None => Some(default),
+    };
+    let fen = format!("{position} {} {}", clock("hmvc", 0)?, clock("fmvn", 1)?);
+    Some(Epd { fen, operations })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epd::parse_epd;
+
+    #[test]
+    fn epd_operations() {
+        let epd = parse_epd(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+        )
+        .unwrap();
+        assert_eq!(
+            epd.fen,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+        assert_eq!(epd.best_moves(), ["Qg6"]);
+        assert!(epd.avoid_moves().is_empty());
+        assert_eq!(epd.id(), Some("WAC.001"));
+
+        let epd = parse_epd(
+            "r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - \
+             bm Qd1+ Qd2; am Nb5; id \"semi;colon test\"; hmvc 4; fmvn 7;",
+        )
+        .unwrap();
+        assert_eq!(epd.best_moves(), ["Qd1+", "Qd2"]);
+        assert_eq!(epd.avoid_moves(), ["Nb5"]);
+        assert_eq!(epd.id(), Some("semi;colon test"));
+        assert!(epd.fen.ends_with(" KQkq - 4 7"));
+    }
+
+    #[test]
+    fn epd_perft_counts() {
+        let epd = parse_epd("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - D2 400; D1 20;")
+            .unwrap();
+        assert_eq!(epd.perft_counts(), [(1, 20), (2, 400)]);
+        let bare = parse_epd("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert!(bare.operations.is_empty());
+        assert!(bare.perft_counts().is_empty());
+    }
+
+    #[test]
+    fn epd_malformed() {
+        for line in [
+            "",
+            "4k3/8/8/8/8/8/8/4K3 w",
+            "4k3/8/8/8/8/8/8/8 w - - bm Kd1;",
+            "4k3/8/8/8/8/8/8/4K3 x - - bm Kd1;",
+            "4k3/8/8/8/8/8/8/4K3 w - - bm Kd1",
+            "4k3/8/8/8/8/8/8/4K3 w - - id \"open;",
+            "4k3/8/8/8/8/8/8/4K3 w - - hmvc x;",
+        ] {
+            assert_eq!(parse_epd(line), None, "{line}");
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{bit_board::BitBoardGame, piece::PlayerColour};
+
+/// piece values in centipawns, in `Piece` order
+pub const CENTIPAWN_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+/// non pawn material counted towards the game phase, in `Piece` order
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+/// phase of the starting position, anything above is clamped
+const MAX_PHASE: i32 = 24;
+
+// piece-square tables from white's side with a8 first
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    15, 15, 15, 15, 15, 15, 15, 15,
+     5,  5,  5,  5,  5,  5,  5,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+const MG_TABLES: [&[i32; 64]; 6] = [&PAWN_MG, &KNIGHT, &BISHOP, &ROOK, &QUEEN, &KING_MG];
+const EG_TABLES: [&[i32; 64]; 6] = [&PAWN_EG, &KNIGHT, &BISHOP, &ROOK, &QUEEN, &KING_EG];
+
+/// Evaluate `game` in centipawns for the side to move, material plus
+/// piece-square tables tapered from midgame to endgame as pieces come off
+pub fn evaluate(game: &BitBoardGame) -> i32 {
+    let mut mg = 0;
+    let mut eg = 0;
+    let mut phase = 0;
+    for white in [true, false] {
+        let sign = if white { 1 } else { -1 };
+        for (piece, mut pieces) in game.board.piece_type_masks(white).into_iter().enumerate() {
+            while pieces != 0 {
+                let square = pieces.trailing_zeros() as usize;
+                // tables start at a8 and square indices at h1
+                let index = if white { 63 - square } else { square ^ 7 };
+                mg += sign * (CENTIPAWN_VALUES[piece] + MG_TABLES[piece][index]);
+                eg += sign * (CENTIPAWN_VALUES[piece] + EG_TABLES[piece][index]);
+                phase += PHASE_WEIGHTS[piece];
+                pieces &= pieces - 1;
+            }
+        }
+    }
+    let phase = phase.min(MAX_PHASE);
+    let score = (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE;
+    if game.turn() == PlayerColour::White {
+        score
+    } else {
+        -score
+    }
+}
+
+/// A static evaluation for search, in centipawns for the side to move
+pub trait Evaluator {
+    fn eval(&self, game: &BitBoardGame) -> i32;
+}
+
+/// counts material only, using `CENTIPAWN_VALUES`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+    fn eval(&self, game: &BitBoardGame) -> i32 {
+        let white = game.turn() == PlayerColour::White;
+        game.material_weighted(white, &CENTIPAWN_VALUES)
+            - game.material_weighted(!white, &CENTIPAWN_VALUES)
+    }
+}
+
+/// the tapered piece-square evaluation of `evaluate`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TaperedEvaluator;
+
+impl Evaluator for TaperedEvaluator {
+    fn eval(&self, game: &BitBoardGame) -> i32 {
+        evaluate(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bit_board::BitBoardGame,
+        eval::{evaluate, Evaluator, MaterialEvaluator},
+        game::ChessGame,
+    };
+
+    #[test]
+    fn eval_symmetry() {
+        assert_eq!(evaluate(&BitBoardGame::new()), 0);
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R b - - 0 1",
+            "4k3/8/8/8/8/8/3P4/3QK3 w - - 0 1",
+        ] {
+            // flipping also hands the move to the other side
+            let gs = BitBoardGame::from_fen(fen).unwrap();
+            assert_eq!(evaluate(&gs), evaluate(&gs.flipped()), "{fen}");
+        }
+    }
+
+    #[test]
+    fn eval_side_to_move() {
+        let white = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let black = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(evaluate(&white) > 800);
+        assert_eq!(evaluate(&white), -evaluate(&black));
+    }
+
+    #[test]
+    fn eval_tapers_king_placement() {
+        // a centralised king is worse with every piece on and better without
+        let start = BitBoardGame::new();
+        let walked =
+            BitBoardGame::from_fen("rnbqkbnr/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1BNR w kq - 0 1")
+                .unwrap();
+        assert!(evaluate(&walked) < evaluate(&start));
+        let bare = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let central = BitBoardGame::from_fen("4k3/8/8/8/4K3/8/8/8 w - - 0 1").unwrap();
+        assert!(evaluate(&central) > evaluate(&bare));
+    }
+
+    #[test]
+    fn material_evaluator() {
+        assert_eq!(MaterialEvaluator.eval(&BitBoardGame::new()), 0);
+        let white = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/R2QK3 w - - 0 1").unwrap();
+        assert_eq!(MaterialEvaluator.eval(&white), 1400);
+        assert_eq!(MaterialEvaluator.eval(&white.flipped()), 1400);
+        let black = BitBoardGame::from_fen("4k3/8/8/8/8/8/8/R2QK3 b - - 0 1").unwrap();
+        assert_eq!(MaterialEvaluator.eval(&black), -1400);
+    }
+}
@@ -1,18 +1,26 @@
 use std::fmt::Display;
 
-use crate::notation::AlgebraicMove;
+use crate::{bit_board::square_coords, notation::AlgebraicMove};
 
 pub trait Move: Sized + Display {
     fn to_uci(&self) -> String;
 }
 
+/// packed `BitBoardGame` moves, see `bit_board::square_index` for the
+/// square numbering
 impl Move for u16 {
     fn to_uci(&self) -> String {
-        let ox = ('h' as u8 - (self & 7) as u8) as char;
-        let oy = ('1' as u8 + ((self >> 3) & 7) as u8) as char;
-        let nx = ('h' as u8 - ((self >> 6) & 7) as u8) as char;
-        let ny = ('1' as u8 + ((self >> 9) & 7) as u8) as char;
-        format!("{ox}{oy}{nx}{ny}")
+        let square = |square: u16| {
+            let (file, rank) = square_coords((square & 63) as u8);
+            format!("{}{}", (b'a' + file) as char, (b'1' + rank) as char)
+        };
+        let (from, to) = (square(*self), square(self >> 6));
+        if self & (1 << 14) == 0 {
+            format!("{from}{to}")
+        } else {
+            let promo = ['n', 'b', 'r', 'q'][((self >> 12) & 3) as usize];
+            format!("{from}{to}{promo}")
+        }
     }
 }
 
@@ -26,4 +34,118 @@ pub trait ChessGame: Sized + Clone {
     fn moves(&self) -> Vec<Self::Move>;
     fn do_move(&mut self, mov: &Self::Move) -> Self::UnMove;
     fn unmove(&mut self, mov: &Self::UnMove);
+
+    /// the legal moves written as UCI strings
+    fn legal_uci(&self) -> Vec<String> {
+        self.moves().iter().map(Move::to_uci).collect()
+    }
+}
+
+/// A game keeping the undo record of every move played, for takebacks and
+/// stepping back and forth through a line. Moves taken back stay in the line
+/// until a different move is pushed in their place
+pub struct GameHistory<G: ChessGame> {
+    game: G,
+    line: Vec<G::Move>,
+    undos: Vec<G::UnMove>,
+}
+
+impl<G: ChessGame> GameHistory<G> {
+    pub fn new(game: G) -> Self {
+        Self {
+            game,
+            line: Vec::new(),
+            undos: Vec::new(),
+        }
+    }
+
+    /// the position after the moves played so far
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// number of moves played from the starting position
+    pub fn ply(&self) -> usize {
+        self.undos.len()
+    }
+
+    /// every move of the line, including ones taken back
+    pub fn line(&self) -> &[G::Move] {
+        &self.line
+    }
+
+    /// Play `mov`, dropping any moves of the line that had been taken back
+    pub fn push(&mut self, mov: G::Move) {
+        self.line.truncate(self.ply());
+        self.undos.push(self.game.do_move(&mov));
+        self.line.push(mov);
+    }
+
+    /// Take back the last move played, false if there wasn't one
+    pub fn pop(&mut self) -> bool {
+        match self.undos.pop() {
+            Some(undo) => {
+                self.game.unmove(&undo);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move to the position `ply` moves into the line, backwards or forwards,
+    /// false leaving the game as it is if the line is shorter than that
+    pub fn goto(&mut self, ply: usize) -> bool {
+        if ply > self.line.len() {
+            return false;
+        }
+        while self.ply() > ply {
+            self.pop();
+        }
+        while self.ply() < ply {
+            let undo = self.game.do_move(&self.line[self.ply()]);
+            self.undos.push(undo);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bit_board::BitBoardGame,
+        game::{ChessGame, GameHistory},
+    };
+
+    #[test]
+    fn history_navigation() {
+        let mut history = GameHistory::new(BitBoardGame::new());
+        assert!(!history.pop());
+        for uci in "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7".split(' ') {
+            let mov = history.game().move_from_uci(uci).unwrap();
+            history.push(mov);
+        }
+        let end = "r1bqk2r/1pppbppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 4 6";
+        assert_eq!(history.ply(), 10);
+        assert_eq!(history.game().to_fen(), end);
+
+        assert!(history.goto(3));
+        assert_eq!(
+            history.game().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert!(!history.goto(11));
+        assert_eq!(history.ply(), 3);
+        assert!(history.goto(10));
+        assert_eq!(history.game().to_fen(), end);
+
+        // a new move after a takeback replaces the rest of the line
+        assert!(history.goto(2));
+        assert!(history.pop());
+        let mov = history.game().move_from_uci("d7d5").unwrap();
+        history.push(mov);
+        assert_eq!(history.line().len(), 2);
+        assert!(!history.goto(3));
+        assert!(history.goto(0));
+        assert_eq!(history.game().to_fen(), BitBoardGame::new().to_fen());
+    }
 }
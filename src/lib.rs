@@ -1,22 +1,54 @@
 // #![feature(generic_const_exprs)]
 // #![allow(incomplete_features)]
 // #![feature(adt_const_params)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    fs, io,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "std")]
 use rayon::prelude::*;
 
+#[cfg(feature = "std")]
 use game::ChessGame;
 
-use crate::game::Move;
+#[cfg(feature = "std")]
+use crate::{bit_board::BitBoardGame, game::Move};
 
 pub mod bit_board;
+#[cfg(feature = "std")]
 pub mod board;
+#[cfg(feature = "std")]
+pub mod book;
+#[cfg(feature = "std")]
 pub mod engine;
+#[cfg(feature = "std")]
+pub mod epd;
+#[cfg(feature = "std")]
+pub mod eval;
+#[cfg(feature = "std")]
 pub mod game;
+#[cfg(feature = "std")]
 pub mod notation;
+#[cfg(feature = "std")]
 pub mod perft_bb_mover;
+#[cfg(feature = "std")]
 pub mod pgn;
 pub mod piece;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod tt;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod wire;
 
+#[cfg(feature = "std")]
 pub fn perft<Game: ChessGame>(gs: &mut Game, limit: usize) -> usize {
     if limit == 0 {
         1
@@ -34,6 +66,7 @@ pub fn perft<Game: ChessGame>(gs: &mut Game, limit: usize) -> usize {
     }
 }
 
+#[cfg(feature = "std")]
 pub fn perft_par<Game: ChessGame + Sync>(gs: &mut Game, limit: usize) -> usize {
     if limit == 0 {
         1
@@ -55,6 +88,85 @@ pub fn perft_par<Game: ChessGame + Sync>(gs: &mut Game, limit: usize) -> usize {
     }
 }
 
+/// Node counts at every depth from 1 to `limit` in a single traversal,
+/// `perft_stats(gs, 3)` on the start position is `[20, 400, 8902]`
+#[cfg(feature = "std")]
+pub fn perft_stats<Game: ChessGame>(gs: &mut Game, limit: usize) -> Vec<usize> {
+    fn walk<Game: ChessGame>(gs: &mut Game, depth: usize, counts: &mut [usize]) {
+        let moves = gs.moves();
+        counts[depth] += moves.len();
+        if depth + 1 == counts.len() {
+            return;
+        }
+        for mov in moves {
+            let unmov = gs.do_move(&mov);
+            walk(gs, depth + 1, counts);
+            gs.unmove(&unmov);
+        }
+    }
+    let mut counts = vec![0; limit];
+    if limit > 0 {
+        walk(gs, 0, &mut counts);
+    }
+    counts
+}
+
+/// Time a perft to `depth`, returning the node count, how long it took and
+/// nodes per second
+#[cfg(feature = "std")]
+pub fn perft_bench<Game: ChessGame>(gs: &mut Game, depth: usize) -> (usize, Duration, f64) {
+    let start = Instant::now();
+    let nodes = perft(gs, depth);
+    let elapsed = start.elapsed();
+    // guard against a zero duration on coarse clocks
+    let nps = nodes as f64 / elapsed.as_secs_f64().max(1e-9);
+    (nodes, elapsed, nps)
+}
+
+/// `perft` caching the count below each position by its Polyglot hash and
+/// depth. The hash covers castling rights and the capturable en passant file
+/// so positions with the same pieces but different moves never share counts.
+#[cfg(feature = "std")]
+pub fn perft_hashed(gs: &BitBoardGame, limit: usize) -> usize {
+    fn walk(gs: &BitBoardGame, depth: usize, cache: &mut HashMap<(u64, usize), usize>) -> usize {
+        if depth <= 1 {
+            return if depth == 0 { 1 } else { gs.moves().len() };
+        }
+        let key = (gs.polyglot_hash(), depth);
+        if let Some(&count) = cache.get(&key) {
+            return count;
+        }
+        let mut count = 0;
+        for mov in gs.moves() {
+            let mut child = gs.clone();
+            child.do_move(&mov);
+            count += walk(&child, depth - 1, cache);
+        }
+        cache.insert(key, count);
+        count
+    }
+    walk(gs, limit, &mut HashMap::new())
+}
+
+/// Leaf count of a perft to `limit` and how many of those leaves have the
+/// side to move in check, the "checks" column of published perft results
+#[cfg(feature = "std")]
+pub fn perft_checks(gs: &BitBoardGame, limit: usize) -> (usize, usize) {
+    if limit == 0 {
+        return (1, gs.in_check() as usize);
+    }
+    let mut totals = (0, 0);
+    for mov in gs.moves() {
+        let mut child = gs.clone();
+        child.do_move(&mov);
+        let (nodes, checks) = perft_checks(&child, limit - 1);
+        totals.0 += nodes;
+        totals.1 += checks;
+    }
+    totals
+}
+
+#[cfg(feature = "std")]
 pub fn perft_div<Game: ChessGame>(gs: &mut Game, limit: usize) -> usize {
     let mut total = 0;
     for mov in gs.moves().iter() {
@@ -68,9 +180,156 @@ pub fn perft_div<Game: ChessGame>(gs: &mut Game, limit: usize) -> usize {
     total
 }
 
-#[cfg(test)]
+/// a `perft_div_checked` run, the root moves are in UCI
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq)]
+pub struct CheckedDivide {
+    /// the bitboard perft count below each of its root moves
+    pub counts: Vec<(String, usize)>,
+    pub total: usize,
+    /// root moves the bitboard generator makes and `GameState` doesn't
+    pub bitboard_only: Vec<String>,
+    /// root moves `GameState` makes and the bitboard generator doesn't
+    pub naive_only: Vec<String>,
+}
+
+#[cfg(feature = "std")]
+impl CheckedDivide {
+    /// true if both generators made the same root moves
+    pub fn agrees(&self) -> bool {
+        self.bitboard_only.is_empty() && self.naive_only.is_empty()
+    }
+}
+
+/// `perft_div` of `fen` on `BitBoardGame` with every root move checked
+/// against the moves `GameState` makes for the same FEN, the moves only one
+/// of them makes are printed after the counts. None if either fails to
+/// parse `fen`
+#[cfg(feature = "std")]
+pub fn perft_div_checked(fen: &str, limit: usize) -> Option<CheckedDivide> {
+    let mut bb = BitBoardGame::from_fen(fen)?;
+    let naive = board::GameState::from_fen(fen)?.legal_uci();
+    let mut counts = Vec::new();
+    let mut bitboard_only = Vec::new();
+    for mov in bb.moves() {
+        let uci = mov.to_uci();
+        let unmov = bb.do_move(&mov);
+        let count = perft(&mut bb, limit.saturating_sub(1));
+        bb.unmove(&unmov);
+        println!("{uci}: {count}");
+        if !naive.contains(&uci) {
+            bitboard_only.push(uci.clone());
+        }
+        counts.push((uci, count));
+    }
+    let naive_only: Vec<String> = naive
+        .into_iter()
+        .filter(|uci| counts.iter().all(|(bb_uci, _)| bb_uci != uci))
+        .collect();
+    let total = counts.iter().map(|(_, count)| count).sum();
+    println!("total: {total}");
+    for uci in &bitboard_only {
+        println!("bitboard only: {uci}");
+    }
+    for uci in &naive_only {
+        println!("naive only: {uci}");
+    }
+    Some(CheckedDivide {
+        counts,
+        total,
+        bitboard_only,
+        naive_only,
+    })
+}
+
+/// `perft` calling `progress(root_index, subtotal)` as the count below each
+/// root move is finished, for showing progress on long runs
+#[cfg(feature = "std")]
+pub fn perft_with_progress<Game: ChessGame, F: FnMut(usize, usize)>(
+    gs: &mut Game,
+    limit: usize,
+    mut progress: F,
+) -> usize {
+    if limit == 0 {
+        return 1;
+    }
+    let mut total = 0;
+    for (index, mov) in gs.moves().iter().enumerate() {
+        let unmov = gs.do_move(mov);
+        let count = perft(gs, limit - 1);
+        gs.unmove(&unmov);
+        total += count;
+        progress(index, count);
+    }
+    total
+}
+
+/// outcome of one depth of one line of a perft suite
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq)]
+pub struct PerftResult {
+    pub fen: String,
+    pub depth: usize,
+    pub expected: usize,
+    /// None when the FEN could not be parsed
+    pub actual: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl PerftResult {
+    pub fn passed(&self) -> bool {
+        self.actual == Some(self.expected)
+    }
+}
+
+/// Run every `fen ;D1 20 ;D2 400` line of a perft suite, blank lines and
+/// lines starting with `#` are skipped
+#[cfg(feature = "std")]
+pub fn perft_suite(suite: &str) -> Vec<PerftResult> {
+    let mut results = Vec::new();
+    for line in suite.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or("").trim();
+        let game = BitBoardGame::from_fen(fen);
+        for field in fields {
+            let Some((depth, expected)) = field.trim().split_once(' ') else {
+                continue;
+            };
+            let (Some(Ok(depth)), Ok(expected)) = (
+                depth.strip_prefix('D').map(str::parse),
+                expected.trim().parse(),
+            ) else {
+                continue;
+            };
+            results.push(PerftResult {
+                fen: fen.to_string(),
+                depth,
+                expected,
+                actual: game.clone().map(|mut gs| perft(&mut gs, depth)),
+            });
+        }
+    }
+    results
+}
+
+/// `perft_suite` on the contents of the file at `path`
+#[cfg(feature = "std")]
+pub fn run_perft_suite(path: &str) -> io::Result<Vec<PerftResult>> {
+    Ok(perft_suite(&fs::read_to_string(path)?))
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
-    use crate::{bit_board::BitBoardGame, game::ChessGame, perft, perft_div};
+    use crate::{
+        bit_board::BitBoardGame,
+        board::GameState,
+        game::{ChessGame, Move},
+        perft, perft_bench, perft_checks, perft_div, perft_div_checked, perft_hashed, perft_stats,
+        perft_suite, perft_with_progress, PerftResult,
+    };
     // game boards from https://www.chessprogramming.org/Perft_Results
     #[test]
     fn perft_base() {
@@ -123,6 +382,30 @@ mod tests {
         assert_eq!(perft(&mut gs, 7), 178633661);
     }
 
+    #[test]
+    fn perft_check_counts() {
+        let kiwipete = BitBoardGame::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+        )
+        .unwrap();
+        let expected = [(48, 0), (2039, 3), (97862, 993), (4085603, 25523)];
+        for (depth, counts) in expected.into_iter().enumerate() {
+            assert_eq!(perft_checks(&kiwipete, depth + 1), counts);
+        }
+
+        let pos3 = BitBoardGame::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -").unwrap();
+        let expected = [
+            (14, 2),
+            (191, 10),
+            (2812, 267),
+            (43238, 1680),
+            (674624, 52950),
+        ];
+        for (depth, counts) in expected.into_iter().enumerate() {
+            assert_eq!(perft_checks(&pos3, depth + 1), counts);
+        }
+    }
+
     #[test]
     fn perft_pos4() {
         let mut gs = BitBoardGame::from_fen(
@@ -175,4 +458,210 @@ mod tests {
         assert_eq!(perft(&mut gs, 4), 3894594);
         // assert_eq!(perft(&mut gs, 5), 164075551);
     }
+
+    #[test]
+    fn perft_suite_results() {
+        let suite = "
+            # positions from https://www.chessprogramming.org/Perft_Results
+            rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902
+            8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 14 ;D2 191 ;D3 2812
+
+            r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1 ;D1 6 ;D2 265
+            not a fen ;D1 1
+        ";
+        let results = perft_suite(suite);
+        assert_eq!(results.len(), 9);
+        let failed: Vec<&PerftResult> = results.iter().filter(|r| !r.passed()).collect();
+        assert_eq!(
+            failed,
+            [
+                &PerftResult {
+                    fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"
+                        .to_string(),
+                    depth: 2,
+                    expected: 265,
+                    actual: Some(264),
+                },
+                &PerftResult {
+                    fen: "not a fen".to_string(),
+                    depth: 1,
+                    expected: 1,
+                    actual: None,
+                },
+            ]
+        );
+    }
+
+    fn sorted_ucis<Game: ChessGame>(gs: &Game) -> Vec<String> {
+        let mut moves: Vec<String> = gs.moves().iter().map(|mov| mov.to_uci()).collect();
+        moves.sort();
+        moves
+    }
+
+    /// assert both move generators agree on every position `depth` plies on
+    fn assert_same_moves(bb: &BitBoardGame, gs: &mut GameState, depth: usize, line: &str) {
+        assert_eq!(
+            sorted_ucis(bb),
+            sorted_ucis(gs),
+            "{} after{line}",
+            bb.to_fen()
+        );
+        if depth <= 1 {
+            return;
+        }
+        for mov in bb.moves() {
+            let uci = mov.to_uci();
+            let gs_mov = gs.moves().into_iter().find(|m| m.to_uci() == uci).unwrap();
+            let mut next = bb.clone();
+            next.do_move(&mov);
+            let undo = gs.do_move(&gs_mov);
+            assert_same_moves(&next, gs, depth - 1, &format!("{line} {uci}"));
+            gs.unmove(&undo);
+        }
+    }
+
+    #[test]
+    fn differential_move_generation() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        ] {
+            let bb = BitBoardGame::from_fen(fen).unwrap();
+            let mut gs = GameState::from_fen(fen).unwrap();
+            assert_same_moves(&bb, &mut gs, 3, "");
+        }
+    }
+
+    #[test]
+    fn checked_divide() {
+        for (fen, moves) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                20,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+                48,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", 14),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                6,
+            ),
+            (
+                "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+                6,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                44,
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                46,
+            ),
+        ] {
+            let divide = perft_div_checked(fen, 1).unwrap();
+            assert!(divide.agrees(), "{fen}: {divide:?}");
+            assert_eq!(divide.total, moves, "{fen}");
+            assert_eq!(divide.counts.len(), moves);
+            assert!(divide.counts.iter().all(|&(_, count)| count == 1));
+        }
+        let divide = perft_div_checked("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", 2).unwrap();
+        assert_eq!(divide.total, 191);
+        assert!(perft_div_checked("not a fen", 1).is_none());
+    }
+
+    #[test]
+    fn uci_coordinates_agree() {
+        let bb = BitBoardGame::new();
+        let bb_move = bb.moves().into_iter().find(|mov| mov.mov == 11 | 27 << 6);
+        assert_eq!(bb_move.unwrap().to_uci(), "e2e4");
+
+        let gs = GameState::new();
+        let gs_move = gs
+            .moves()
+            .into_iter()
+            .find(|mov| (mov.from, mov.to) == (12, 28));
+        assert_eq!(gs_move.unwrap().to_uci(), "e2e4");
+    }
+
+    #[test]
+    fn perft_stats_per_depth() {
+        let mut gs = BitBoardGame::new();
+        assert_eq!(perft_stats(&mut gs, 4), [20, 400, 8902, 197281]);
+        assert!(perft_stats(&mut gs, 0).is_empty());
+        let mut gs = GameState::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -").unwrap();
+        assert_eq!(perft_stats(&mut gs, 3), [14, 191, 2812]);
+    }
+
+    #[test]
+    fn legal_uci_strings() {
+        let moves = BitBoardGame::new().legal_uci();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.iter().any(|m| m == "g1f3"));
+        let mut bb = moves;
+        let mut gs = GameState::new().legal_uci();
+        bb.sort();
+        gs.sort();
+        assert_eq!(bb, gs);
+    }
+
+    #[test]
+    fn perft_bench_counts() {
+        let mut gs = BitBoardGame::new();
+        let (nodes, _, nps) = perft_bench(&mut gs, 3);
+        assert_eq!(nodes, perft(&mut gs, 3));
+        assert!(nps > 0.0);
+    }
+
+    #[test]
+    fn hashed_perft_irreversible_state() {
+        // Ke1-f1-e1 and Ne3-g4-e3 reach the same pieces with and without
+        // castling rights three plies in
+        let castling = BitBoardGame::from_fen("r3k2r/8/8/8/8/4N3/8/R3K2R w KQkq - 0 1").unwrap();
+        let no_castling = BitBoardGame::from_fen("r3k2r/8/8/8/8/4N3/8/R3K2R w - - 0 1").unwrap();
+        assert_ne!(castling.polyglot_hash(), no_castling.polyglot_hash());
+        for (gs, depth) in [(&castling, 5), (&no_castling, 5)] {
+            assert_eq!(perft_hashed(gs, depth), perft(&mut gs.clone(), depth));
+        }
+        assert_ne!(perft_hashed(&castling, 2), perft_hashed(&no_castling, 2));
+
+        // e2e4 and e2e3 e3e4 reach the same pieces, only the first can be
+        // taken en passant
+        let ep = BitBoardGame::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(perft_hashed(&ep, 6), perft(&mut ep.clone(), 6));
+    }
+
+    #[test]
+    fn perft_progress_calls() {
+        for (fen, depth, expected) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                3,
+                8902,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+                2,
+                2039,
+            ),
+        ] {
+            let mut gs = BitBoardGame::from_fen(fen).unwrap();
+            let mut calls = Vec::new();
+            let total =
+                perft_with_progress(&mut gs, depth, |index, count| calls.push((index, count)));
+            assert_eq!(total, expected);
+            assert_eq!(calls.iter().map(|&(_, count)| count).sum::<usize>(), total);
+            let indices: Vec<_> = calls.iter().map(|&(index, _)| index).collect();
+            assert_eq!(indices, (0..gs.moves().len()).collect::<Vec<_>>());
+        }
+        let mut gs = BitBoardGame::new();
+        assert_eq!(perft_with_progress(&mut gs, 0, |_, _| panic!()), 1);
+    }
 }
@@ -1,8 +1,11 @@
+use std::fmt::Display;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
 use crate::piece::Piece;
 
+#[derive(Debug, PartialEq, Clone)]
 pub enum AlgebraicPosition {
     Square(u8, u8),
     Piece(Piece),
@@ -11,6 +14,7 @@ pub enum AlgebraicPosition {
     SquarePiece(u8, u8, Piece),
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub enum AlgebraicMove {
     Move(AlgebraicPosition, AlgebraicPosition),
     Promotion(AlgebraicPosition, AlgebraicPosition, Piece),
@@ -30,10 +34,10 @@ pub fn parse_square(inp: &str) -> Option<(u8, u8)> {
     let mut chrs = inp.chars();
     let file = chrs.next().unwrap();
     let rank = chrs.next().unwrap();
-    if file >= 'a' && file <= 'h' && rank >= '1' && rank <= '8' {
-        return Some((rank as u8 - '1' as u8, file as u8 - 'a' as u8));
+    if ('a'..='h').contains(&file) && ('1'..='8').contains(&rank) {
+        return Some((rank as u8 - b'1', file as u8 - b'a'));
     }
-    return None;
+    None
 }
 
 pub fn parse_rank(inp: &str) -> Option<u8> {
@@ -42,10 +46,10 @@ pub fn parse_rank(inp: &str) -> Option<u8> {
     }
     let mut chrs = inp.chars();
     let rank = chrs.next().unwrap();
-    if rank >= '1' && rank <= '8' {
-        return Some(rank as u8 - '1' as u8);
+    if ('1'..='8').contains(&rank) {
+        return Some(rank as u8 - b'1');
     }
-    return None;
+    None
 }
 
 pub fn parse_file(inp: &str) -> Option<u8> {
@@ -54,10 +58,10 @@ pub fn parse_file(inp: &str) -> Option<u8> {
     }
     let mut chrs = inp.chars();
     let file = chrs.next().unwrap();
-    if file >= 'a' && file <= 'h' {
-        return Some(file as u8 - 'a' as u8);
+    if ('a'..='h').contains(&file) {
+        return Some(file as u8 - b'a');
     }
-    return None;
+    None
 }
 
 pub const fn parse_piece_letter(inp: char) -> Option<Piece> {
@@ -72,72 +76,248 @@ pub const fn parse_piece_letter(inp: char) -> Option<Piece> {
     }
 }
 
+pub const fn piece_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "",
+        Piece::Knight => "N",
+        Piece::Bishop => "B",
+        Piece::Rook => "R",
+        Piece::Queen => "Q",
+        Piece::King => "K",
+    }
+}
+
 use AlgebraicMove::*;
 use AlgebraicPosition::*;
 
 lazy_static! {
-    static ref ALG_OPT_PARSE: Regex = Regex::new(r"([NBRQK])?x?([a-h][1-8])(=[BNRQ])?").unwrap();
     static ref ALG_PARSE: Regex =
-        Regex::new(r"([NBRQK])?([a-h])?([1-8])?x?([a-h][1-8])(=[BNRQ])?").unwrap();
+        Regex::new(r"^([NBRQK])?([a-h])?([1-8])?x?([a-h][1-8])(=?[NBRQ])?$").unwrap();
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NotationError {
+    /// text that isn't shaped like a move
+    Unrecognised(String),
+    /// text shaped like a move that no piece could make (e.g. a knight promoting)
+    Invalid(String),
 }
 
-pub fn str_to_algebraic(inp: &str) -> Option<AlgebraicMove> {
-    let inp = inp.trim();
-    Some(if inp.starts_with("O-O-O") {
-        AlgebraicMove::QSCastle
-    } else if inp.starts_with("O-O") {
-        AlgebraicMove::KSCastle
-    } else if let Some(caps) = ALG_OPT_PARSE.captures(inp) {
-        let moving_piece_type = match caps.get(1) {
-            None => Piece::Pawn,
-            Some(piece) => parse_piece_letter(piece.as_str().chars().next()?)?,
-        };
-        match (caps.get(2)?, caps.get(3)) {
-            (sqr, None) => {
-                let (r, f) = parse_square(sqr.as_str())?;
-                Move(Piece(moving_piece_type), Square(r, f))
+impl Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotationError::Unrecognised(mov) => write!(f, "unknown move \"{mov}\""),
+            NotationError::Invalid(mov) => write!(f, "impossible move \"{mov}\""),
+        }
+    }
+}
+
+/// Parse a move in standard algebraic notation, ignoring any trailing
+/// check or annotation symbols
+pub fn str_to_algebraic(inp: &str) -> Result<AlgebraicMove, NotationError> {
+    let mov = inp.trim().trim_end_matches(['+', '#', '!', '?']);
+    match mov {
+        "O-O-O" | "0-0-0" => return Ok(QSCastle),
+        "O-O" | "0-0" => return Ok(KSCastle),
+        _ => {}
+    }
+    let unrecognised = || NotationError::Unrecognised(inp.to_string());
+    let caps = ALG_PARSE.captures(mov).ok_or_else(unrecognised)?;
+    let moving_piece_type = match caps.get(1) {
+        None => Piece::Pawn,
+        Some(piece) => parse_piece_letter(piece.as_str().chars().next().ok_or_else(unrecognised)?)
+            .ok_or_else(unrecognised)?,
+    };
+    let moving_piece = match (caps.get(2), caps.get(3)) {
+        (Some(f), Some(r)) => SquarePiece(
+            parse_rank(r.as_str()).ok_or_else(unrecognised)?,
+            parse_file(f.as_str()).ok_or_else(unrecognised)?,
+            moving_piece_type,
+        ),
+        (Some(f), None) => FilePiece(
+            parse_file(f.as_str()).ok_or_else(unrecognised)?,
+            moving_piece_type,
+        ),
+        (None, Some(r)) => RankPiece(
+            parse_rank(r.as_str()).ok_or_else(unrecognised)?,
+            moving_piece_type,
+        ),
+        (None, None) => Piece(moving_piece_type),
+    };
+    let (r, f) = parse_square(&caps[4]).ok_or_else(unrecognised)?;
+    match caps.get(5) {
+        None => Ok(Move(moving_piece, Square(r, f))),
+        Some(promo) => {
+            if moving_piece_type != Piece::Pawn || (r != 0 && r != 7) {
+                return Err(NotationError::Invalid(inp.to_string()));
             }
-            (sqr, Some(promo)) => {
-                assert!(caps.get(1).is_none());
-                let (r, f) = parse_square(sqr.as_str())?;
-                Promotion(
-                    Piece(Piece::Pawn),
-                    Square(r, f),
-                    parse_piece_letter(promo.as_str().chars().nth(2)?)?,
-                )
+            let promo_piece = promo.as_str().chars().last().and_then(parse_piece_letter);
+            Ok(Promotion(
+                moving_piece,
+                Square(r, f),
+                promo_piece.ok_or_else(unrecognised)?,
+            ))
+        }
+    }
+}
+
+fn write_position(pos: &AlgebraicPosition, out: &mut String) {
+    match *pos {
+        Square(r, f) => {
+            out.push((b'a' + f) as char);
+            out.push((b'1' + r) as char);
+        }
+        Piece(p) => out.push_str(piece_letter(p)),
+        RankPiece(r, p) => {
+            out.push_str(piece_letter(p));
+            out.push((b'1' + r) as char);
+        }
+        FilePiece(f, p) => {
+            out.push_str(piece_letter(p));
+            out.push((b'a' + f) as char);
+        }
+        SquarePiece(r, f, p) => {
+            out.push_str(piece_letter(p));
+            out.push((b'a' + f) as char);
+            out.push((b'1' + r) as char);
+        }
+    }
+}
+
+impl AlgebraicMove {
+    /// Render as SAN, marking captures with `x` and ending in `#` for mate
+    /// or `+` for check
+    pub fn to_san_string(&self, capture: bool, check: bool, mate: bool) -> String {
+        let mut out = String::new();
+        match self {
+            Move(from, to) | Promotion(from, to, _) => {
+                write_position(from, &mut out);
+                if capture {
+                    out.push('x');
+                }
+                write_position(to, &mut out);
+                if let Promotion(_, _, p) = self {
+                    out.push('=');
+                    out.push_str(piece_letter(*p));
+                }
             }
+            KSCastle => out.push_str("O-O"),
+            QSCastle => out.push_str("O-O-O"),
+        }
+        if mate {
+            out.push('#');
+        } else if check {
+            out.push('+');
         }
-    } else if let Some(caps) = ALG_PARSE.captures(inp) {
-        let moving_piece_type = match caps.get(1) {
-            None => Piece::Pawn,
-            Some(piece) => parse_piece_letter(piece.as_str().chars().next()?)?,
-        };
-        let moving_piece = match (caps.get(2), caps.get(3)) {
-            (Some(f), Some(r)) => SquarePiece(
-                parse_rank(r.as_str())?,
-                parse_file(f.as_str())?,
-                moving_piece_type,
+        out
+    }
+}
+
+/// SAN without capture or check markers, except that a pawn naming its
+/// file is always written as a capture
+impl Display for AlgebraicMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let capture = matches!(
+            self,
+            Move(FilePiece(_, Piece::Pawn), _) | Promotion(FilePiece(_, Piece::Pawn), _, _)
+        );
+        f.write_str(&self.to_san_string(capture, false, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        notation::{str_to_algebraic, AlgebraicMove::*, AlgebraicPosition::*, NotationError},
+        piece::Piece,
+    };
+
+    #[test]
+    fn parse_san() {
+        assert_eq!(
+            str_to_algebraic("e4"),
+            Ok(Move(Piece(Piece::Pawn), Square(3, 4)))
+        );
+        assert_eq!(
+            str_to_algebraic("exd5"),
+            Ok(Move(FilePiece(4, Piece::Pawn), Square(4, 3)))
+        );
+        assert_eq!(
+            str_to_algebraic("Nbd2"),
+            Ok(Move(FilePiece(1, Piece::Knight), Square(1, 3)))
+        );
+        assert_eq!(
+            str_to_algebraic("R1a3+"),
+            Ok(Move(RankPiece(0, Piece::Rook), Square(2, 0)))
+        );
+        assert_eq!(
+            str_to_algebraic("Qh4xe1#"),
+            Ok(Move(SquarePiece(3, 7, Piece::Queen), Square(0, 4)))
+        );
+        assert_eq!(
+            str_to_algebraic("fxg8=N"),
+            Ok(Promotion(
+                FilePiece(5, Piece::Pawn),
+                Square(7, 6),
+                Piece::Knight
+            ))
+        );
+        assert_eq!(str_to_algebraic("O-O-O"), Ok(QSCastle));
+        assert_eq!(str_to_algebraic("O-O+"), Ok(KSCastle));
+    }
+
+    #[test]
+    fn san_string() {
+        let capture = Promotion(FilePiece(4, Piece::Pawn), Square(7, 3), Piece::Queen);
+        assert_eq!(capture.to_san_string(true, false, false), "exd8=Q");
+        let rook = Move(SquarePiece(0, 0, Piece::Rook), Square(3, 0));
+        assert_eq!(rook.to_san_string(false, true, false), "Ra1a4+");
+        assert_eq!(QSCastle.to_san_string(false, true, true), "O-O-O#");
+        assert_eq!(
+            Move(Piece(Piece::Pawn), Square(3, 4)).to_san_string(false, false, false),
+            "e4"
+        );
+    }
+
+    #[test]
+    fn display_variants() {
+        let cases = [
+            (Move(Piece(Piece::Pawn), Square(3, 4)), "e4"),
+            (Move(Piece(Piece::Knight), Square(5, 5)), "Nf6"),
+            (Move(FilePiece(4, Piece::Pawn), Square(4, 3)), "exd5"),
+            (Move(FilePiece(1, Piece::Knight), Square(1, 3)), "Nbd2"),
+            (Move(RankPiece(0, Piece::Rook), Square(2, 0)), "R1a3"),
+            (Move(SquarePiece(3, 7, Piece::Queen), Square(0, 4)), "Qh4e1"),
+            (
+                Promotion(Piece(Piece::Pawn), Square(7, 0), Piece::Queen),
+                "a8=Q",
             ),
-            (Some(f), None) => FilePiece(parse_file(f.as_str())?, moving_piece_type),
-            (None, Some(r)) => RankPiece(parse_rank(r.as_str())?, moving_piece_type),
-            (None, None) => Piece(moving_piece_type),
-        };
-        match (caps.get(4)?, caps.get(5)) {
-            (sqr, None) => {
-                let (r, f) = parse_square(sqr.as_str())?;
-                Move(moving_piece, Square(r, f))
-            }
-            (sqr, Some(promo)) => {
-                assert!(caps.get(1).is_none());
-                let (r, f) = parse_square(sqr.as_str())?;
-                Promotion(
-                    Piece(Piece::Pawn),
-                    Square(r, f),
-                    parse_piece_letter(promo.as_str().chars().nth(2)?)?,
-                )
-            }
+            (
+                Promotion(FilePiece(6, Piece::Pawn), Square(0, 7), Piece::Knight),
+                "gxh1=N",
+            ),
+            (KSCastle, "O-O"),
+            (QSCastle, "O-O-O"),
+        ];
+        for (mov, san) in cases {
+            assert_eq!(mov.to_string(), san);
+            assert_eq!(str_to_algebraic(san), Ok(mov));
         }
-    } else {
-        panic!("unkown move \"{inp}\"")
-    })
+    }
+
+    #[test]
+    fn parse_san_garbage() {
+        for garbage in ["", "hello", "Zz9", "e9", "i4", "e4 e5", "Nf3 is good", "=Q"] {
+            assert_eq!(
+                str_to_algebraic(garbage),
+                Err(NotationError::Unrecognised(garbage.to_string()))
+            );
+        }
+        for invalid in ["Nf8=Q", "e5=Q"] {
+            assert_eq!(
+                str_to_algebraic(invalid),
+                Err(NotationError::Invalid(invalid.to_string()))
+            );
+        }
+    }
 }
@@ -1,4 +1,7 @@
-use crate::bit_board::{BitBoard, OnMove};
+use crate::{
+    bit_board::{promotion_flags, BitBoard, BitBoardGame, OnMove},
+    game::Move,
+};
 
 pub struct PerftMove {
     pub depth_target: u64,
@@ -20,61 +23,13 @@ impl OnMove for PerftMove {
         } else {
             let mut b = me.clone();
             b.mov(from, to);
-            match (
+            let rights = (
                 from != 7 && to != 7 && WQ,
                 from != 0 && to != 0 && WK,
                 from != 63 && to != 63 && BQ,
                 from != 56 && to != 56 && BK,
-            ) {
-                (true, true, true, true) => {
-                    b.gen_moves::<true, true, true, true, Self>(!turn, self, None)
-                }
-                (true, true, true, false) => {
-                    b.gen_moves::<true, true, true, false, Self>(!turn, self, None)
-                }
-                (true, true, false, true) => {
-                    b.gen_moves::<true, true, false, true, Self>(!turn, self, None)
-                }
-                (true, true, false, false) => {
-                    b.gen_moves::<true, true, false, false, Self>(!turn, self, None)
-                }
-                (true, false, true, true) => {
-                    b.gen_moves::<true, false, true, true, Self>(!turn, self, None)
-                }
-                (true, false, true, false) => {
-                    b.gen_moves::<true, false, true, false, Self>(!turn, self, None)
-                }
-                (true, false, false, true) => {
-                    b.gen_moves::<true, false, false, true, Self>(!turn, self, None)
-                }
-                (true, false, false, false) => {
-                    b.gen_moves::<true, false, false, false, Self>(!turn, self, None)
-                }
-                (false, true, true, true) => {
-                    b.gen_moves::<false, true, true, true, Self>(!turn, self, None)
-                }
-                (false, true, true, false) => {
-                    b.gen_moves::<false, true, true, false, Self>(!turn, self, None)
-                }
-                (false, true, false, true) => {
-                    b.gen_moves::<false, true, false, true, Self>(!turn, self, None)
-                }
-                (false, true, false, false) => {
-                    b.gen_moves::<false, true, false, false, Self>(!turn, self, None)
-                }
-                (false, false, true, true) => {
-                    b.gen_moves::<false, false, true, true, Self>(!turn, self, None)
-                }
-                (false, false, true, false) => {
-                    b.gen_moves::<false, false, true, false, Self>(!turn, self, None)
-                }
-                (false, false, false, true) => {
-                    b.gen_moves::<false, false, false, true, Self>(!turn, self, None)
-                }
-                (false, false, false, false) => {
-                    b.gen_moves::<false, false, false, false, Self>(!turn, self, None)
-                }
-            }
+            );
+            b.gen_moves_dyn(!turn, self, None, rights);
         }
         self.depth -= 1;
     }
@@ -92,7 +47,11 @@ impl OnMove for PerftMove {
         } else {
             let mut b = me.clone();
             b.mov(from, to);
-            b.gen_moves::<false, false, BQ, BK, Self>(!turn, self, None);
+            if turn {
+                b.gen_moves_dyn(!turn, self, None, (false, false, BQ, BK));
+            } else {
+                b.gen_moves_dyn(!turn, self, None, (WQ, WK, false, false));
+            }
         }
         self.depth -= 1;
     }
@@ -115,7 +74,7 @@ impl OnMove for PerftMove {
             } else {
                 b.clear(to + 8);
             }
-            b.gen_moves::<WQ, WK, BQ, BK, Self>(!turn, self, None);
+            b.gen_moves_dyn(!turn, self, None, (WQ, WK, BQ, BK));
         }
         self.depth -= 1;
     }
@@ -133,11 +92,11 @@ impl OnMove for PerftMove {
             if turn {
                 b.mov(7, 4);
                 b.mov(3, 5);
-                b.gen_moves::<false, false, BQ, BK, Self>(!turn, self, None);
+                b.gen_moves_dyn(!turn, self, None, (false, false, BQ, BK));
             } else {
                 b.mov(63, 60);
                 b.mov(59, 61);
-                b.gen_moves::<WQ, WK, false, false, Self>(!turn, self, None);
+                b.gen_moves_dyn(!turn, self, None, (WQ, WK, false, false));
             }
         }
         self.depth -= 1;
@@ -156,11 +115,11 @@ impl OnMove for PerftMove {
             if turn {
                 b.mov(0, 2);
                 b.mov(3, 1);
-                b.gen_moves::<false, false, BQ, BK, Self>(!turn, self, None);
+                b.gen_moves_dyn(!turn, self, None, (false, false, BQ, BK));
             } else {
                 b.mov(56, 58);
                 b.mov(59, 57);
-                b.gen_moves::<WQ, WK, false, false, Self>(!turn, self, None);
+                b.gen_moves_dyn(!turn, self, None, (WQ, WK, false, false));
             }
         }
         self.depth -= 1;
@@ -179,10 +138,10 @@ impl OnMove for PerftMove {
             let mut b = me.clone();
             if turn {
                 b.mov(from, from + 16);
-                b.gen_moves::<WQ, WK, BQ, BK, Self>(!turn, self, Some(from + 8));
+                b.gen_moves_dyn(!turn, self, Some(from + 8), (WQ, WK, BQ, BK));
             } else {
                 b.mov(from, from - 16);
-                b.gen_moves::<WQ, WK, BQ, BK, Self>(!turn, self, Some(from - 8));
+                b.gen_moves_dyn(!turn, self, Some(from - 8), (WQ, WK, BQ, BK));
             }
         }
         self.depth -= 1;
@@ -199,3 +158,443 @@ impl OnMove for PerftMove {
         todo!()
     }
 }
+
+/// perft on the callback generator split by root move
+pub struct PerftDivide {
+    inner: PerftMove,
+    results: Vec<(String, u64)>,
+}
+
+impl PerftDivide {
+    pub fn new(depth: u64) -> Self {
+        Self {
+            inner: PerftMove {
+                depth_target: depth,
+                depth: 0,
+                counter: 0,
+            },
+            results: Vec::new(),
+        }
+    }
+
+    /// (uci, subtree count) for each root move, sorted by uci
+    pub fn results(&self) -> Vec<(String, u64)> {
+        let mut results = self.results.clone();
+        results.sort();
+        results
+    }
+
+    pub fn total(&self) -> u64 {
+        self.inner.counter
+    }
+
+    /// print each root move's count followed by the total
+    pub fn print(&self) {
+        for (uci, count) in self.results() {
+            println!("{uci}: {count}");
+        }
+        println!("total: {}", self.total());
+    }
+
+    fn record(&mut self, mov: u16, before: u64) {
+        self.results
+            .push((mov.to_uci(), self.inner.counter - before));
+    }
+}
+
+impl OnMove for PerftDivide {
+    fn on_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+    ) {
+        let before = self.inner.counter;
+        self.inner.on_move::<WQ, WK, BQ, BK>(turn, me, from, to);
+        self.record(((to as u16) << 6) + from as u16, before);
+    }
+
+    fn on_king_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+    ) {
+        let before = self.inner.counter;
+        self.inner
+            .on_king_move::<WQ, WK, BQ, BK>(turn, me, from, to);
+        self.record(((to as u16) << 6) + from as u16, before);
+    }
+
+    fn on_ep_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+    ) {
+        let before = self.inner.counter;
+        self.inner.on_ep_move::<WQ, WK, BQ, BK>(turn, me, from, to);
+        self.record(((to as u16) << 6) + from as u16, before);
+    }
+
+    fn on_pawn_push2<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+    ) {
+        let before = self.inner.counter;
+        self.inner.on_pawn_push2::<WQ, WK, BQ, BK>(turn, me, from);
+        let to = if turn { from + 16 } else { from - 16 };
+        self.record(((to as u16) << 6) + from as u16, before);
+    }
+
+    fn on_promotion<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+        piece: u8,
+    ) {
+        let before = self.inner.counter;
+        self.inner
+            .on_promotion::<WQ, WK, BQ, BK>(turn, me, from, to, piece);
+        self.record(
+            promotion_flags(piece) + ((to as u16) << 6) + from as u16,
+            before,
+        );
+    }
+
+    fn on_qs_castle<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+    ) {
+        let before = self.inner.counter;
+        self.inner.on_qs_castle::<WQ, WK, BQ, BK>(turn, me);
+        self.record(if turn { (5 << 6) + 3 } else { (61 << 6) + 59 }, before);
+    }
+
+    fn on_ks_castle<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+    ) {
+        let before = self.inner.counter;
+        self.inner.on_ks_castle::<WQ, WK, BQ, BK>(turn, me);
+        self.record(if turn { (1 << 6) + 3 } else { (57 << 6) + 59 }, before);
+    }
+}
+
+/// perft leaf counters as published on chessprogramming.org
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+}
+
+/// perft on the callback generator categorising every leaf move
+pub struct DetailedPerft {
+    pub depth_target: u64,
+    depth: u64,
+    pub stats: PerftStats,
+}
+
+impl DetailedPerft {
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth_target: depth,
+            depth: 0,
+            stats: PerftStats::default(),
+        }
+    }
+
+    /// The stats of every leaf `depth_target` plies below `game`, the
+    /// position itself being the only leaf at depth 0
+    pub fn run(mut self, game: &BitBoardGame) -> PerftStats {
+        if self.depth_target == 0 {
+            return PerftStats {
+                nodes: 1,
+                ..PerftStats::default()
+            };
+        }
+        game.proc_movs(&mut self);
+        self.stats
+    }
+
+    /// count `b`, reached by `turn` moving, if it is a leaf otherwise search on
+    fn visit(
+        &mut self,
+        b: &BitBoard,
+        turn: bool,
+        capture: bool,
+        ep: Option<u8>,
+        rights: (bool, bool, bool, bool),
+    ) {
+        self.depth += 1;
+        // past the target only when run from a depth 0 search, count rather
+        // than search forever
+        if self.depth >= self.depth_target {
+            self.stats.nodes += 1;
+            if capture {
+                self.stats.captures += 1;
+            }
+            if b.check_mask(!turn) != u64::MAX {
+                self.stats.checks += 1;
+            }
+        } else {
+            b.gen_moves_dyn(!turn, self, ep, rights);
+        }
+        self.depth -= 1;
+    }
+
+    fn at_leaf(&self) -> bool {
+        self.depth + 1 == self.depth_target
+    }
+}
+
+impl OnMove for DetailedPerft {
+    fn on_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+    ) {
+        let mut b = me.clone();
+        b.mov(from, to);
+        let rights = (
+            from != 7 && to != 7 && WQ,
+            from != 0 && to != 0 && WK,
+            from != 63 && to != 63 && BQ,
+            from != 56 && to != 56 && BK,
+        );
+        let capture = me.piece_mask() & (1 << to) != 0;
+        self.visit(&b, turn, capture, None, rights);
+    }
+
+    fn on_king_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+    ) {
+        let mut b = me.clone();
+        b.mov(from, to);
+        let rights = (WQ && !turn, WK && !turn, BQ && turn, BK && turn);
+        let capture = me.piece_mask() & (1 << to) != 0;
+        self.visit(&b, turn, capture, None, rights);
+    }
+
+    fn on_ep_move<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+    ) {
+        let mut b = me.clone();
+        b.mov(from, to);
+        if turn {
+            b.clear(to - 8);
+        } else {
+            b.clear(to + 8);
+        }
+        if self.at_leaf() {
+            self.stats.en_passants += 1;
+        }
+        self.visit(&b, turn, true, None, (WQ, WK, BQ, BK));
+    }
+
+    fn on_pawn_push2<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+    ) {
+        let mut b = me.clone();
+        let (to, ep) = if turn {
+            (from + 16, from + 8)
+        } else {
+            (from - 16, from - 8)
+        };
+        b.mov(from, to);
+        self.visit(&b, turn, false, Some(ep), (WQ, WK, BQ, BK));
+    }
+
+    fn on_promotion<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+        from: u8,
+        to: u8,
+        piece: u8,
+    ) {
+        let mut b = me.clone();
+        b.clear(from);
+        b.set(to, piece);
+        let rights = (to != 7 && WQ, to != 0 && WK, to != 63 && BQ, to != 56 && BK);
+        if self.at_leaf() {
+            self.stats.promotions += 1;
+        }
+        let capture = me.piece_mask() & (1 << to) != 0;
+        self.visit(&b, turn, capture, None, rights);
+    }
+
+    fn on_qs_castle<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+    ) {
+        let mut b = me.clone();
+        let rights = if turn {
+            b.mov(7, 4);
+            b.mov(3, 5);
+            (false, false, BQ, BK)
+        } else {
+            b.mov(63, 60);
+            b.mov(59, 61);
+            (WQ, WK, false, false)
+        };
+        if self.at_leaf() {
+            self.stats.castles += 1;
+        }
+        self.visit(&b, turn, false, None, rights);
+    }
+
+    fn on_ks_castle<const WQ: bool, const WK: bool, const BQ: bool, const BK: bool>(
+        &mut self,
+        turn: bool,
+        me: &BitBoard,
+    ) {
+        let mut b = me.clone();
+        let rights = if turn {
+            b.mov(0, 2);
+            b.mov(3, 1);
+            (false, false, BQ, BK)
+        } else {
+            b.mov(56, 58);
+            b.mov(59, 57);
+            (WQ, WK, false, false)
+        };
+        if self.at_leaf() {
+            self.stats.castles += 1;
+        }
+        self.visit(&b, turn, false, None, rights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bit_board::BitBoardGame,
+        game::{ChessGame, Move},
+        perft,
+        perft_bb_mover::{DetailedPerft, PerftDivide, PerftMove, PerftStats},
+    };
+
+    fn fast_perft(gs: &BitBoardGame, depth: u64) -> u64 {
+        let mut mover = PerftMove {
+            depth_target: depth,
+            depth: 0,
+            counter: 0,
+        };
+        gs.proc_movs(&mut mover);
+        mover.counter
+    }
+
+    #[test]
+    fn fast_perft_en_passant() {
+        for fen in [
+            // en passant available at the root
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1",
+            // en passant only after a double push in the tree
+            "4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1",
+            // en passant capture that would expose the king
+            "8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1",
+            "8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1",
+            "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        ] {
+            let mut gs = BitBoardGame::from_fen(fen).unwrap();
+            for depth in 1..=4 {
+                assert_eq!(
+                    fast_perft(&gs, depth) as usize,
+                    perft(&mut gs, depth as usize),
+                    "{fen} depth {depth}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fast_perft_divide() {
+        let mut gs = BitBoardGame::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let mut divide = PerftDivide::new(3);
+        gs.proc_movs(&mut divide);
+        let mut expected: Vec<_> = gs
+            .moves()
+            .iter()
+            .map(|mov| {
+                let mut child = gs.clone();
+                child.do_move(mov);
+                (mov.to_uci(), perft(&mut child, 2) as u64)
+            })
+            .collect();
+        expected.sort();
+        assert_eq!(divide.results(), expected);
+        assert_eq!(divide.total() as usize, perft(&mut gs, 3));
+    }
+
+    fn detailed(fen: &str, depth: u64) -> PerftStats {
+        DetailedPerft::new(depth).run(&BitBoardGame::from_fen(fen).unwrap())
+    }
+
+    fn stats(counts: [u64; 6]) -> PerftStats {
+        let [nodes, captures, en_passants, castles, promotions, checks] = counts;
+        PerftStats {
+            nodes,
+            captures,
+            en_passants,
+            castles,
+            promotions,
+            checks,
+        }
+    }
+
+    // https://www.chessprogramming.org/Perft_Results
+    #[test]
+    fn detailed_perft_reference() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(detailed(kiwipete, 1), stats([48, 8, 0, 2, 0, 0]));
+        assert_eq!(detailed(kiwipete, 2), stats([2039, 351, 1, 91, 0, 3]));
+        assert_eq!(
+            detailed(kiwipete, 3),
+            stats([97862, 17102, 45, 3162, 0, 993])
+        );
+        assert_eq!(detailed(kiwipete, 0), stats([1, 0, 0, 0, 0, 0]));
+        let position3 = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        assert_eq!(detailed(position3, 1), stats([14, 1, 0, 0, 0, 2]));
+        assert_eq!(detailed(position3, 2), stats([191, 14, 0, 0, 0, 10]));
+        assert_eq!(detailed(position3, 3), stats([2812, 209, 2, 0, 0, 267]));
+        assert_eq!(
+            detailed(position3, 4),
+            stats([43238, 3348, 123, 0, 0, 1680])
+        );
+        let position4 = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        assert_eq!(detailed(position4, 1), stats([6, 0, 0, 0, 0, 0]));
+        assert_eq!(detailed(position4, 2), stats([264, 87, 0, 6, 48, 10]));
+        assert_eq!(detailed(position4, 3), stats([9467, 1021, 4, 0, 120, 38]));
+    }
+}
@@ -4,7 +4,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use streaming_iterator::StreamingIterator;
 
-use crate::notation::{self, AlgebraicMove};
+use crate::notation::{self, AlgebraicMove, NotationError};
 
 pub struct StrIter<'a, Reader: Iterator<Item = String>> {
     line: Option<String>,
@@ -37,6 +37,9 @@ impl<'a, T: Iterator<Item = String>> StreamingIterator for StrIter<'a, T> {
 
 pub struct PGNFileReader<'a, Reader: StreamingIterator<Item = str>> {
     reader: &'a mut Reader,
+    /// a line read past the end of the previous game, the first tag of the
+    /// next one
+    pending: Option<String>,
 }
 
 pub struct PGNChessGame {
@@ -47,41 +50,397 @@ pub struct PGNChessGame {
 pub fn read_pgn_file<'a, Reader: StreamingIterator<Item = str>>(
     reader: &'a mut Reader,
 ) -> PGNFileReader<'a, Reader> {
-    PGNFileReader { reader }
+    PGNFileReader {
+        reader,
+        pending: None,
+    }
 }
 
 lazy_static! {
-    static ref MOVE_MATCH: Regex = Regex::new(r"([0-9]+)\. ?([1-8xa-hBNRQKO\-\+#]+) (\{[^\}]*\})? ?([0-9]+\.\.\.)? ?([1-8xa-hBNRQKO\-\+#]+ )?").unwrap();
+    static ref TAG_PAIR: Regex =
+        Regex::new(r#"^\[([A-Za-z0-9_]+)\s+"((?:[^"\\]|\\.)*)"\s*\]$"#).unwrap();
+    static ref MOVE_NUMBER: Regex = Regex::new(r"^[0-9]+\.+").unwrap();
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PGNError {
+    /// a `[...]` line that isn't a valid tag pair
+    BadTag(String),
+    /// a movetext token that couldn't be read as a move
+    BadMove(NotationError),
+    /// a comment or variation left open at the end of the game
+    Unterminated,
+}
+
+impl<'a, T: StreamingIterator<Item = str>> PGNFileReader<'a, T> {
+    fn next_line(&mut self) -> Option<String> {
+        self.pending
+            .take()
+            .or_else(|| self.reader.next().map(str::to_string))
+    }
 }
 
 impl<'a, T: StreamingIterator<Item = str>> Iterator for PGNFileReader<'a, T> {
-    type Item = PGNChessGame;
+    type Item = Result<PGNChessGame, PGNError>;
 
+    /// A game is a block of tag pairs then movetext up to a result, a blank
+    /// line or the next tag. The whole game is consumed even when part of it
+    /// is malformed so the following game still reads cleanly
     fn next(&mut self) -> Option<Self::Item> {
+        let mut line = loop {
+            let line = self.next_line()?;
+            if !line.trim().is_empty() {
+                break Some(line);
+            }
+        };
+
         let mut meta = HashMap::new();
-        while {
-            let mut line = self.reader.next()?;
-            if line.starts_with('[') {
-                line = line.trim_start_matches('[');
-                line = line.trim_end_matches(']');
-                let (key, val) = line.split_once(" ")?;
-                meta.insert(key.to_string(), val.to_string());
-                true
-            } else if line == "" {
-                true
-            } else {
-                false
+        let mut bad_tag = None;
+        while let Some(tag) = line.as_deref().filter(|l| l.starts_with('[')) {
+            match TAG_PAIR.captures(tag.trim()) {
+                Some(caps) => {
+                    meta.insert(caps[1].to_string(), unescape_tag_value(&caps[2]));
+                }
+                None => {
+                    bad_tag.get_or_insert_with(|| PGNError::BadTag(tag.to_string()));
+                }
+            }
+            line = self.next_line();
+        }
+        // blank lines between the tags and the movetext
+        while line.as_deref().is_some_and(|l| l.trim().is_empty()) {
+            line = self.next_line();
+        }
+
+        // movetext may be wrapped over several lines so read up to the result
+        let mut movetext = String::new();
+        while let Some(text) = line {
+            if text.starts_with('[') {
+                self.pending = Some(text);
+                break;
+            }
+            if text.trim().is_empty() {
+                break;
             }
-        } {}
-        let mut moves = Vec::new();
-        for i in MOVE_MATCH.captures_iter(self.reader.get()?) {
-            let white_move = i.get(2).unwrap().as_str();
-            moves.push(notation::str_to_algebraic(white_move).unwrap());
-            if let Some(mov) = i.get(5) {
-                moves.push(notation::str_to_algebraic(mov.as_str()).unwrap())
+            movetext.push_str(&text);
+            movetext.push('\n');
+            if ends_with_result(&movetext) {
+                break;
             }
+            line = self.next_line();
+        }
+
+        if let Some(err) = bad_tag {
+            return Some(Err(err));
         }
+        Some(parse_movetext(&movetext).map(|moves| PGNChessGame { moves, meta }))
+    }
+}
+
+/// read the moves of the main line, skipping move numbers, comments,
+/// NAGs, annotation glyphs and variations
+fn parse_movetext(movetext: &str) -> Result<Vec<AlgebraicMove>, PGNError> {
+    let mut moves = Vec::new();
+    let mut variation_depth = 0;
+    let mut chars = movetext.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' => {
+                chars
+                    .find(|(_, c)| *c == '}')
+                    .ok_or(PGNError::Unterminated)?;
+            }
+            ';' => {
+                chars.find(|(_, c)| *c == '\n');
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut end = movetext.len();
+                while let Some((i, c)) = chars.peek() {
+                    if c.is_whitespace() || "{;()".contains(*c) {
+                        end = *i;
+                        break;
+                    }
+                    chars.next();
+                }
+                if variation_depth > 0 {
+                    continue;
+                }
+                let token = &movetext[start..end];
+                if token.starts_with('$') || matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    continue;
+                }
+                let token = MOVE_NUMBER.replace(token, "");
+                let token = token.trim_end_matches(['!', '?', '+', '#']);
+                if token.is_empty() {
+                    continue;
+                }
+                moves.push(notation::str_to_algebraic(token).map_err(PGNError::BadMove)?);
+            }
+        }
+    }
+    if variation_depth != 0 {
+        return Err(PGNError::Unterminated);
+    }
+    Ok(moves)
+}
+
+fn ends_with_result(movetext: &str) -> bool {
+    matches!(
+        movetext.split_whitespace().last(),
+        Some("1-0" | "0-1" | "1/2-1/2" | "*")
+    )
+}
+
+/// undo the `\\` and `\"` escapes allowed inside a tag value
+fn unescape_tag_value(val: &str) -> String {
+    let mut out = String::with_capacity(val.len());
+    let mut chars = val.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn escape_tag_value(val: &str) -> String {
+    val.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// tags that lead the header block, in their standard order
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// movetext lines are wrapped to stay within this width
+const PGN_LINE_WIDTH: usize = 80;
+
+/// Serialise a game to PGN, tag pairs first followed by numbered movetext
+/// ending in the game's result (`*` if no `Result` tag is present)
+pub fn write_pgn(game: &PGNChessGame) -> String {
+    let mut out = String::new();
+
+    let mut other_tags: Vec<_> = game
+        .meta
+        .keys()
+        .filter(|k| !SEVEN_TAG_ROSTER.contains(&k.as_str()))
+        .collect();
+    other_tags.sort();
+    let tags = SEVEN_TAG_ROSTER
+        .iter()
+        .filter_map(|k| game.meta.get_key_value(*k))
+        .chain(other_tags.into_iter().map(|k| (k, &game.meta[k])));
+    for (key, val) in tags {
+        out.push_str(&format!("[{key} \"{}\"]\n", escape_tag_value(val)));
+    }
+    out.push('\n');
+
+    let result = match game.meta.get("Result") {
+        Some(res) => res.clone(),
+        None => "*".to_string(),
+    };
+    let mut tokens = Vec::with_capacity(game.moves.len() * 3 / 2 + 1);
+    for (i, mov) in game.moves.iter().enumerate() {
+        if i % 2 == 0 {
+            tokens.push(format!("{}.", i / 2 + 1));
+        }
+        tokens.push(mov.to_string());
+    }
+    tokens.push(result);
+
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len != 0 && line_len + 1 + token.len() > PGN_LINE_WIDTH {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len != 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        notation::{str_to_algebraic, NotationError},
+        pgn::{read_pgn_file, write_pgn, PGNChessGame, PGNError, StrIter},
+    };
+
+    fn read_single(pgn: &str) -> PGNChessGame {
+        let mut lines = pgn.lines().map(|l| l.to_string());
+        let mut reader = StrIter::new(&mut lines);
+        let game = read_pgn_file(&mut reader).next();
+        game.unwrap().unwrap()
+    }
+
+    #[test]
+    fn pgn_round_trip() {
+        let pgn = "[Event \"Club Match\"]
+[Site \"London\"]
+[Date \"2023.06.21\"]
+[Round \"3\"]
+[White \"Smith\"]
+[Black \"Jones\"]
+[Result \"1/2-1/2\"]
+[ECO \"C84\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1+ b5 7. Bb3 d6 1/2-1/2
+";
+        let game = read_single(pgn);
+        assert_eq!(game.moves.len(), 14);
+        // check annotations aren't kept by the parser so come back without the `+`
+        assert_eq!(write_pgn(&game), pgn.replace("Re1+", "Re1"));
+    }
+
+    #[test]
+    fn pgn_tag_values() {
+        let game = read_single(
+            r#"[White "Carlsen, Magnus"]
+[Black ""]
+[Annotator "Someone \"quoted\" \\ here"]
+
+1. e4 e5 *
+"#,
+        );
+        assert_eq!(game.meta["White"], "Carlsen, Magnus");
+        assert_eq!(game.meta["Black"], "");
+        assert_eq!(game.meta["Annotator"], r#"Someone "quoted" \ here"#);
+        assert!(write_pgn(&game).contains(r#"[Annotator "Someone \"quoted\" \\ here"]"#));
+    }
+
+    #[test]
+    fn pgn_multiline_movetext() {
+        let pgn = "[Event \"Shuffle\"]
+[Result \"1/2-1/2\"]
+
+1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 5. Nf3 Nf6 6. Ng1 Ng8 7. Nf3 Nf6 8.
+Ng1 Ng8 9. Nf3 Nf6 10. Ng1 Ng8 11. Nf3 Nf6 12. Ng1 Ng8 13. Nf3 Nf6 14. Ng1 Ng8
+15. Nf3 Nf6 16. Ng1 Ng8 17. Nf3 Nf6 18. Ng1 Ng8 19. Nf3 Nf6 20. Ng1 Ng8 21. Nf3
+Nf6 22. Ng1 Ng8 23. Nf3 Nf6 24. Ng1 Ng8 25. Nf3 Nf6 26. Ng1 Ng8 27. Nf3 Nf6 28.
+Ng1 Ng8 29. Nf3 Nf6 30. Ng1 Ng8 31. Nf3 Nf6 32. Ng1 Ng8 33. Nf3 Nf6 34. Ng1 Ng8
+35. Nf3 Nf6 36. Ng1 Ng8 37. Nf3 Nf6 38. Ng1 Ng8 39. Nf3 Nf6 40. Ng1 Ng8 1/2-1/2
+";
+        let game = read_single(pgn);
+        assert_eq!(game.moves.len(), 80);
+        assert_eq!(write_pgn(&game), pgn);
+    }
+
+    #[test]
+    fn pgn_annotations() {
+        let game = read_single(
+            "[Event \"Annotated\"]
+
+1. e4 {best by test} e5 $1 2. Nf3!? (2. f4 exf4 (2... d5) 3. Nf3 {gambit}) 2... Nc6
+; a rest of line comment (with a bracket
+3. Bb5 a6 {The Morphy defence} 4. Ba4+? Nf6! 1-0
+",
+        );
+        assert_eq!(game.moves.len(), 8);
+        assert_eq!(
+            write_pgn(&game),
+            "[Event \"Annotated\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 *\n"
+        );
+
+        // castles spelt with zeros aren't taken for move numbers
+        let game = read_single("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5. d3 0-0 *\n");
+        assert_eq!(game.moves.len(), 10);
+        assert_eq!(
+            write_pgn(&game),
+            "\n1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O Nf6 5. d3 O-O *\n"
+        );
+    }
+
+    #[test]
+    fn pgn_malformed() {
+        let pgn = "[Event \"Open\"]
+
+1. e4 (1. d4 d5 *
+
+[Event unquoted]
+
+1. e4 Zz9 *
+
+[Event \"Bad move\"]
+
+1. e4 Zz9 *
+
+[Event \"Fine\"]
+
+1. e4 *
+";
+        let mut lines = pgn.lines().map(|l| l.to_string());
+        let mut reader = StrIter::new(&mut lines);
+        let mut games = read_pgn_file(&mut reader);
+        assert_eq!(games.next().unwrap().err(), Some(PGNError::Unterminated));
+        assert_eq!(
+            games.next().unwrap().err(),
+            Some(PGNError::BadTag("[Event unquoted]".to_string()))
+        );
+        assert_eq!(
+            games.next().unwrap().err(),
+            Some(PGNError::BadMove(NotationError::Unrecognised(
+                "Zz9".to_string()
+            )))
+        );
+        assert_eq!(games.next().unwrap().unwrap().moves.len(), 1);
+        assert!(games.next().is_none());
+    }
+
+    #[test]
+    fn pgn_multiple_games() {
+        let pgn = "
+[Event \"First\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+[Event \"Second\"]
+[Result \"0-1\"]
+1. f3 e5 2. g4 Qh4# 0-1
+
+
+[Event \"Third\"]
+
+1. d4 d5 2. c4
+e6 3. Nc3
+";
+        let mut lines = pgn.lines().map(|l| l.to_string());
+        let mut reader = StrIter::new(&mut lines);
+        let games: Vec<PGNChessGame> = read_pgn_file(&mut reader).map(Result::unwrap).collect();
+        let summary: Vec<(&str, usize)> = games
+            .iter()
+            .map(|game| (game.meta["Event"].as_str(), game.moves.len()))
+            .collect();
+        assert_eq!(summary, [("First", 7), ("Second", 4), ("Third", 5)]);
+    }
 
-        Some(PGNChessGame { moves, meta })
+    #[test]
+    fn pgn_write_wraps_lines() {
+        let moves = ["Nf3", "Nf6", "Ng1", "Ng8"]
+            .iter()
+            .cycle()
+            .take(80)
+            .map(|m| str_to_algebraic(m).unwrap())
+            .collect();
+        let game = PGNChessGame {
+            moves,
+            meta: HashMap::new(),
+        };
+        let written = write_pgn(&game);
+        assert!(written.lines().all(|l| l.len() <= 80));
+        assert!(written.lines().count() > 3);
+        assert_eq!(
+            written.split_whitespace().filter(|t| *t == "Nf3").count(),
+            20
+        );
+        assert!(written.ends_with(" *\n"));
     }
 }
@@ -1,7 +1,7 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PlayerColour {
     White = 0,
     Black = 1,
@@ -109,7 +109,7 @@ impl ColouredPiece {
 }
 
 impl Display for ColouredPiece {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_str(match self {
             ColouredPiece::WhitePawn => "P",
             ColouredPiece::WhiteKnight => "N",
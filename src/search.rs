@@ -0,0 +1,905 @@
+use std::{
+    fs, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use crate::{
+    bit_board::{BitBoardGame, BitBoardGameMove},
+    epd::parse_epd,
+    eval::{Evaluator, TaperedEvaluator},
+    game::ChessGame,
+    tt::{Flag, TTVal, TTable},
+};
+
+/// score for giving mate now, mates further away score `MATE - ply`
+pub const MATE: i32 = 1_000_000;
+/// no search gets this deep so anything past it is a mate score
+const MAX_PLY: i32 = 1000;
+/// size of the table used by searches that aren't given one
+const TABLE_BITS: u8 = 16;
+/// half width of the window iterative deepening searches around the
+/// previous score
+const ASPIRATION_WINDOW: i32 = 50;
+/// how often `negamax` checks the stop flag and deadline
+const STOP_POLL_NODES: u64 = 1024;
+
+/// true if `score` is a forced mate for either side
+pub const fn is_mate_score(score: i32) -> bool {
+    score.abs() > MATE - MAX_PLY
+}
+
+/// mate scores are stored relative to the position rather than the root so
+/// they stay valid wherever the position is reached again
+fn to_tt(score: i32, ply: i32) -> i32 {
+    if is_mate_score(score) {
+        score + score.signum() * ply
+    } else {
+        score
+    }
+}
+
+fn from_tt(score: i32, ply: i32) -> i32 {
+    if is_mate_score(score) {
+        score - score.signum() * ply
+    } else {
+        score
+    }
+}
+
+/// State carried between the nodes of a search
+pub struct SearchState {
+    pub table: TTable,
+    /// the last two quiet moves that caused a beta cutoff at each ply
+    killers: Vec<[u16; 2]>,
+    /// `[from][to]` scores of quiet moves that caused cutoffs
+    history: Box<[[u32; 64]; 64]>,
+    /// aspiration searches that scored at or above their window
+    pub fail_highs: u32,
+    /// aspiration searches that scored at or below their window
+    pub fail_lows: u32,
+    /// packed moves the root is restricted to, every move if empty
+    pub root_moves: Vec<u16>,
+    /// set from another thread to end the search early
+    pub stop: Arc<AtomicBool>,
+    /// the search stops itself once this has passed, it can be set from
+    /// another thread while the search runs
+    pub deadline: Arc<Mutex<Option<Instant>>>,
+    /// the stop flag has been seen, every node returns straight away
+    stopped: bool,
+    /// nodes visited by `negamax`
+    pub nodes: u64,
+    /// hashes of the positions from the root to the current node, earlier
+    /// game positions pushed before searching also count as repeats
+    pub path: Vec<u64>,
+    /// how much the side to move at the root dislikes a draw, positive
+    /// values play on in positions that aren't worse
+    pub contempt: i32,
+}
+
+impl SearchState {
+    /// a fresh state with a table of `1 << table_bits` entries
+    pub fn new(table_bits: u8) -> Self {
+        Self {
+            table: TTable::new(table_bits),
+            killers: Vec::new(),
+            history: Box::new([[0; 64]; 64]),
+            fail_highs: 0,
+            fail_lows: 0,
+            root_moves: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            deadline: Arc::default(),
+            stopped: false,
+            nodes: 0,
+            path: Vec::new(),
+            contempt: 0,
+        }
+    }
+
+    /// true once the search has been stopped, its scores are then
+    /// meaningless
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn killers(&self, ply: usize) -> [u16; 2] {
+        self.killers.get(ply).copied().unwrap_or_default()
+    }
+
+    pub fn history(&self, mov: u16) -> u32 {
+        self.history[(mov & 63) as usize][((mov >> 6) & 63) as usize]
+    }
+
+    /// score of a draw `ply` plies from the root for the side to move there
+    pub fn draw_score(&self, ply: i32) -> i32 {
+        if ply % 2 == 0 {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// true if `hash` is on the path with the same side to move, the last
+    /// entry being the parent of the position
+    pub fn is_repetition(&self, hash: u64) -> bool {
+        self.path
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .any(|&h| h == hash)
+    }
+
+    /// Remember a quiet move that caused a beta cutoff `depth` plies from the
+    /// horizon
+    pub fn record_cutoff(&mut self, mov: u16, ply: usize, depth: u8) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [0; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != mov {
+            killers[1] = killers[0];
+            killers[0] = mov;
+        }
+        let entry = &mut self.history[(mov & 63) as usize][((mov >> 6) & 63) as usize];
+        *entry = entry.saturating_add(u32::from(depth) * u32::from(depth));
+    }
+
+    /// Sort `moves` to search `tt_move` first, then captures and promotions
+    /// by static exchange, then killers at `ply`, then quiet moves by history
+    pub fn order_moves(
+        &self,
+        node: &BitBoardGame,
+        moves: &mut [BitBoardGameMove],
+        tt_move: u16,
+        ply: usize,
+    ) {
+        const NOISY: i32 = 2_000_000;
+        const KILLER: i32 = 1_000_000;
+        let killers = self.killers(ply);
+        moves.sort_by_cached_key(|mov| {
+            let score = if mov.mov == tt_move {
+                i32::MAX
+            } else if !is_quiet(node, mov) {
+                NOISY
+                    + node
+                        .board
+                        .see((mov.mov & 63) as u8, ((mov.mov >> 6) & 63) as u8)
+            } else if mov.mov == killers[0] {
+                KILLER + 1
+            } else if mov.mov == killers[1] {
+                KILLER
+            } else {
+                self.history(mov.mov).min(KILLER as u32 - 1) as i32
+            };
+            -score
+        });
+    }
+}
+
+/// neither a capture nor a promotion
+fn is_quiet(node: &BitBoardGame, mov: &BitBoardGameMove) -> bool {
+    let from = (mov.mov & 63) as u8;
+    let to = ((mov.mov >> 6) & 63) as u8;
+    let en_passant = node.capturable_ep() == Some(to) && node.board.nibble(from) & 0b111 == 0b100;
+    mov.mov & (1 << 14) == 0 && node.board.nibble(to) == 0 && !en_passant
+}
+
+/// Alpha-beta negamax to `depth` plies, scored by `eval` for the side to
+/// move. `ply` is the distance from the root so nearer mates score higher.
+/// Results are stored in the state's table and moves are searched in
+/// `SearchState::order_moves` order. The stop flag and deadline are polled
+/// every `STOP_POLL_NODES` nodes, a stopped search returns 0 from every node.
+/// A position already on the state's path, the fifty move rule and stalemate
+/// are draws scored by `SearchState::draw_score`. The window is narrowed to
+/// the mate scores still reachable from `ply`.
+pub fn negamax<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    state: &mut SearchState,
+    depth: u8,
+    ply: i32,
+    mut alpha: i32,
+    beta: i32,
+) -> i32 {
+    state.nodes += 1;
+    if state.nodes.is_multiple_of(STOP_POLL_NODES)
+        && (state.stop.load(Ordering::Relaxed)
+            || state
+                .deadline
+                .lock()
+                .unwrap()
+                .is_some_and(|deadline| Instant::now() >= deadline))
+    {
+        state.stopped = true;
+    }
+    if state.stopped {
+        return 0;
+    }
+    let hash = node.polyglot_hash();
+    if state.is_repetition(hash) {
+        return state.draw_score(ply);
+    }
+    let mut moves = node.moves();
+    if moves.is_empty() {
+        return if node.in_check() {
+            ply - MATE
+        } else {
+            state.draw_score(ply)
+        };
+    }
+    if node.halfmove_clock() >= 100 {
+        return state.draw_score(ply);
+    }
+    // nothing found here can beat mating on the next move or do worse than
+    // being mated now, a window already past either can't be improved on
+    alpha = alpha.max(ply - MATE);
+    let beta = beta.min(MATE - ply - 1);
+    if alpha >= beta {
+        return alpha;
+    }
+    if depth == 0 {
+        return eval.eval(node);
+    }
+
+    let mut tt_move = 0;
+    if let Some(entry) = state.table.get(hash) {
+        let value = from_tt(entry.value, ply);
+        if entry.depth >= depth {
+            match entry.flag {
+                Flag::Exact => return value,
+                Flag::LowerBound if value >= beta => return value,
+                Flag::UpperBound if value <= alpha => return value,
+                _ => {}
+            }
+        }
+        tt_move = entry.best_move;
+    }
+    state.order_moves(node, &mut moves, tt_move, ply as usize);
+
+    let alpha_orig = alpha;
+    let mut value = -MATE;
+    let mut best = moves[0].mov;
+    state.path.push(hash);
+    for mov in moves {
+        let mut child = node.clone();
+        child.do_move(&mov);
+        let score = -negamax(&child, eval, state, depth - 1, ply + 1, -beta, -alpha);
+        if score > value {
+            value = score;
+            best = mov.mov;
+        }
+        alpha = alpha.max(value);
+        if alpha >= beta {
+            if is_quiet(node, &mov) {
+                state.record_cutoff(mov.mov, ply as usize, depth);
+            }
+            break;
+        }
+    }
+    state.path.pop();
+    if state.stopped {
+        return 0;
+    }
+    let flag = if value <= alpha_orig {
+        Flag::UpperBound
+    } else if value >= beta {
+        Flag::LowerBound
+    } else {
+        Flag::Exact
+    };
+    state.table.insert(TTVal {
+        flag,
+        depth,
+        value: to_tt(value, ply),
+        full_hash: hash,
+        best_move: best,
+    });
+    value
+}
+
+/// The best move and its score searching `depth` plies, None if the game is
+/// over
+pub fn best_move<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    depth: u8,
+) -> Option<(BitBoardGameMove, i32)> {
+    best_move_with_state(node, eval, &mut SearchState::new(TABLE_BITS), depth)
+}
+
+/// `best_move` sharing `state` with earlier searches, the root result is
+/// stored in its table so `BitBoardGame::extract_pv` can follow the line
+pub fn best_move_with_state<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    state: &mut SearchState,
+    depth: u8,
+) -> Option<(BitBoardGameMove, i32)> {
+    // below and above any real score so the result is always exact
+    search_root(node, eval, state, depth, -MATE - 1, MATE + 1)
+}
+
+/// Every legal move with its score from a full window `negamax` of `depth`
+/// plies after it, best first. Slower than `best_move` as no move can cut
+/// the others off, but each score is exact
+pub fn ranked_moves<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    depth: u8,
+) -> Vec<(BitBoardGameMove, i32)> {
+    let mut state = SearchState::new(TABLE_BITS);
+    multi_pv_with_state(node, eval, &mut state, depth, usize::MAX)
+        .into_iter()
+        .map(|line| (line.mov, line.score))
+        .collect()
+}
+
+/// one root move of a multi-PV search
+#[derive(Clone)]
+pub struct PvLine {
+    pub mov: BitBoardGameMove,
+    /// exact score of `mov` for the side to move at the root
+    pub score: i32,
+    /// packed moves of the line, starting with `mov` and continuing with the
+    /// best replies stored in the table
+    pub pv: Vec<u16>,
+}
+
+/// The best `count` root moves searching `depth` plies, best first. Every
+/// root move is searched with a full window so the scores of all of them are
+/// exact, the root is restricted to `state.root_moves` as in
+/// `best_move_with_state`. A stopped search returns the lines of the moves
+/// searched before it stopped
+pub fn multi_pv_with_state<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    state: &mut SearchState,
+    depth: u8,
+    count: usize,
+) -> Vec<PvLine> {
+    let mut moves = node.moves();
+    if !state.root_moves.is_empty() {
+        moves.retain(|mov| state.root_moves.contains(&mov.mov));
+    }
+    let hash = node.polyglot_hash();
+    let tt_move = state.table.get(hash).map_or(0, |entry| entry.best_move);
+    state.order_moves(node, &mut moves, tt_move, 0);
+    let mut lines = Vec::new();
+    state.path.push(hash);
+    for mov in moves {
+        let mut child = node.clone();
+        child.do_move(&mov);
+        let score = -negamax(
+            &child,
+            eval,
+            state,
+            depth.saturating_sub(1),
+            1,
+            -MATE - 1,
+            MATE + 1,
+        );
+        if state.stopped {
+            break;
+        }
+        let mut pv = vec![mov.mov];
+        pv.extend(child.extract_pv(&state.table, depth.saturating_sub(1).into()));
+        lines.push(PvLine { mov, score, pv });
+    }
+    state.path.pop();
+    lines.sort_by_key(|line| -line.score);
+    lines.truncate(count);
+    lines
+}
+
+/// Search the root moves within the window `alpha..beta`, a score at or
+/// outside the window is only a bound on the true score
+fn search_root<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    state: &mut SearchState,
+    depth: u8,
+    alpha: i32,
+    beta: i32,
+) -> Option<(BitBoardGameMove, i32)> {
+    let mut moves = node.moves();
+    if !state.root_moves.is_empty() {
+        moves.retain(|mov| state.root_moves.contains(&mov.mov));
+    }
+    let hash = node.polyglot_hash();
+    let tt_move = state.table.get(hash).map_or(0, |entry| entry.best_move);
+    state.order_moves(node, &mut moves, tt_move, 0);
+    let mut best: Option<(BitBoardGameMove, i32)> = None;
+    let mut best_value = -MATE - 1;
+    state.path.push(hash);
+    for mov in moves {
+        let mut child = node.clone();
+        child.do_move(&mov);
+        let value = -negamax(
+            &child,
+            eval,
+            state,
+            depth.saturating_sub(1),
+            1,
+            -beta,
+            -alpha.max(best_value),
+        );
+        if value > best_value {
+            best_value = value;
+            best = Some((mov, value));
+        }
+        if value >= beta || state.stopped {
+            break;
+        }
+    }
+    state.path.pop();
+    if state.stopped {
+        return best;
+    }
+    if let Some((mov, value)) = &best {
+        let flag = if *value <= alpha {
+            Flag::UpperBound
+        } else if *value >= beta {
+            Flag::LowerBound
+        } else {
+            Flag::Exact
+        };
+        state.table.insert(TTVal {
+            flag,
+            depth,
+            value: *value,
+            full_hash: hash,
+            best_move: mov.mov,
+        });
+    }
+    best
+}
+
+/// `best_move` at depths 1 to `max_depth` in turn, stopping early once a
+/// mate is found, the deepest result is returned
+pub fn iterative_deepening<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    max_depth: u8,
+) -> Option<(BitBoardGameMove, i32)> {
+    iterative_deepening_with_state(node, eval, &mut SearchState::new(TABLE_BITS), max_depth)
+}
+
+/// `iterative_deepening` sharing `state`. Each depth after the first is
+/// searched in a narrow window around the previous score and searched again
+/// with the failing side of the window opened up if the score falls outside.
+/// When the stop flag is set or the deadline passes the result of the last
+/// completed depth is returned, or the partial first depth if none completed.
+pub fn iterative_deepening_with_state<E: Evaluator>(
+    node: &BitBoardGame,
+    eval: &E,
+    state: &mut SearchState,
+    max_depth: u8,
+) -> Option<(BitBoardGameMove, i32)> {
+    let mut best: Option<(BitBoardGameMove, i32)> = None;
+    for depth in 1..=max_depth {
+        let result = match best {
+            Some((_, score)) => {
+                let mut alpha = score - ASPIRATION_WINDOW;
+                let mut beta = score + ASPIRATION_WINDOW;
+                loop {
+                    let result = search_root(node, eval, state, depth, alpha, beta);
+                    match result {
+                        _ if state.stopped => break result,
+                        Some((_, score)) if score <= alpha && alpha > -MATE - 1 => {
+                            state.fail_lows += 1;
+                            alpha = -MATE - 1;
+                        }
+                        Some((_, score)) if score >= beta && beta < MATE + 1 => {
+                            state.fail_highs += 1;
+                            beta = MATE + 1;
+                        }
+                        _ => break result,
+                    }
+                }
+            }
+            None => best_move_with_state(node, eval, state, depth),
+        };
+        if state.stopped {
+            best = best.or(result);
+            break;
+        }
+        best = result;
+        match &best {
+            Some((_, score)) if !is_mate_score(*score) => {}
+            _ => break,
+        }
+    }
+    best
+}
+
+/// outcome of searching one record of a best move suite
+#[derive(Debug, PartialEq)]
+pub struct BestMoveResult {
+    pub id: Option<String>,
+    pub fen: String,
+    /// SAN of the move the search chose, None if there were no moves
+    pub chosen: Option<String>,
+    /// chosen is one of the `bm` moves and none of the `am` moves
+    pub solved: bool,
+}
+
+/// SAN without check, mate or annotation suffixes
+fn bare_san(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+/// Search every EPD record of a WAC/ECM style suite to `depth`, records that
+/// do not parse are skipped
+pub fn bestmove_suite(suite: &str, depth: u8) -> Vec<BestMoveResult> {
+    let mut results = Vec::new();
+    for epd in suite.lines().filter_map(parse_epd) {
+        let Some(game) = BitBoardGame::from_fen(&epd.fen) else {
+            continue;
+        };
+        let chosen = best_move(&game, &TaperedEvaluator, depth).map(|(mov, _)| game.san(&mov));
+        let matches = |moves: &[String]| {
+            chosen
+                .as_deref()
+                .is_some_and(|chosen| moves.iter().any(|san| bare_san(san) == bare_san(chosen)))
+        };
+        let solved = (epd.best_moves().is_empty() || matches(epd.best_moves()))
+            && !matches(epd.avoid_moves())
+            && chosen.is_some();
+        results.push(BestMoveResult {
+            id: epd.id().map(str::to_string),
+            fen: epd.fen,
+            chosen,
+            solved,
+        });
+    }
+    results
+}
+
+/// `bestmove_suite` on the contents of the file at `path`
+pub fn run_bestmove_suite(path: &str, depth: u8) -> io::Result<Vec<BestMoveResult>> {
+    Ok(bestmove_suite(&fs::read_to_string(path)?, depth))
+}
+
+/// summary of a suite run like "231/300 solved"
+pub fn suite_score(results: &[BestMoveResult]) -> String {
+    let solved = results.iter().filter(|result| result.solved).count();
+    format!("{solved}/{} solved", results.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::Ordering,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        bit_board::BitBoardGame,
+        eval::{Evaluator, TaperedEvaluator},
+        game::{ChessGame, Move},
+        search::{
+            best_move, best_move_with_state, bestmove_suite, is_mate_score, iterative_deepening,
+            iterative_deepening_with_state, multi_pv_with_state, negamax, ranked_moves,
+            suite_score, SearchState, MATE,
+        },
+    };
+
+    /// scores every position the same
+    struct ConstantEvaluator;
+
+    impl Evaluator for ConstantEvaluator {
+        fn eval(&self, _game: &BitBoardGame) -> i32 {
+            7
+        }
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let gs = BitBoardGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (mov, score) = best_move(&gs, &TaperedEvaluator, 2).unwrap();
+        assert_eq!(mov.to_uci(), "a1a8");
+        assert_eq!(score, MATE - 1);
+        assert!(is_mate_score(score));
+
+        // a deeper search still prefers the quickest mate
+        let (mov, score) = best_move(&gs, &TaperedEvaluator, 3).unwrap();
+        assert_eq!(mov.to_uci(), "a1a8");
+        assert_eq!(score, MATE - 1);
+    }
+
+    #[test]
+    fn ranked_root_moves() {
+        let gs = BitBoardGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let ranked = ranked_moves(&gs, &TaperedEvaluator, 2);
+        assert_eq!(ranked.len(), gs.moves().len());
+        assert_eq!(ranked[0].0.to_uci(), "a1a8");
+        assert_eq!(ranked[0].1, MATE - 1);
+        assert!(ranked[1..].iter().all(|(_, score)| !is_mate_score(*score)));
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        let mated = BitBoardGame::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(ranked_moves(&mated, &TaperedEvaluator, 2).is_empty());
+    }
+
+    #[test]
+    fn multi_pv_lines() {
+        // d1d5 wins the queen, b1c3 and the rest leave it be
+        let gs = BitBoardGame::from_fen("4k3/8/8/3q4/8/8/8/1N1QK3 w - - 0 1").unwrap();
+        let mut state = SearchState::new(16);
+        let lines = multi_pv_with_state(&gs, &TaperedEvaluator, &mut state, 3, 2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].mov.to_uci(), "d1d5");
+        assert_ne!(lines[0].mov.mov, lines[1].mov.mov);
+        assert!(lines[0].score > lines[1].score);
+        for line in &lines {
+            assert_eq!(line.pv[0], line.mov.mov);
+            assert!(line.pv.len() <= 3);
+            // the line is playable from the root
+            let mut game = gs.clone();
+            for &mov in &line.pv {
+                let next = game.moves().into_iter().find(|m| m.mov == mov).unwrap();
+                game.do_move(&next);
+            }
+        }
+        let (best, score) = best_move(&gs, &TaperedEvaluator, 3).unwrap();
+        assert_eq!((lines[0].mov.mov, lines[0].score), (best.mov, score));
+
+        state.root_moves = vec![gs.move_from_uci("b1c3").unwrap().mov];
+        let lines = multi_pv_with_state(&gs, &TaperedEvaluator, &mut state, 2, 2);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].mov.to_uci(), "b1c3");
+    }
+
+    #[test]
+    fn mate_in_three() {
+        let mut gs = BitBoardGame::from_fen("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1").unwrap();
+        let (_, score) = iterative_deepening(&gs, &TaperedEvaluator, 7).unwrap();
+        assert_eq!(score, MATE - 5);
+
+        // both sides following the search reach the mate on time
+        for ply in 0..5 {
+            let (mov, score) = best_move(&gs, &TaperedEvaluator, 5 - ply).unwrap();
+            let mate = MATE - 5 + ply as i32;
+            assert_eq!(score, if ply % 2 == 0 { mate } else { -mate });
+            gs.do_move(&mov);
+        }
+        assert!(gs.moves().is_empty());
+        assert!(gs.in_check());
+    }
+
+    #[test]
+    fn terminal_scores() {
+        let mated = BitBoardGame::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(
+            negamax(
+                &mated,
+                &TaperedEvaluator,
+                &mut SearchState::new(8),
+                3,
+                0,
+                -MATE,
+                MATE
+            ),
+            -MATE
+        );
+        assert!(best_move(&mated, &TaperedEvaluator, 3).is_none());
+
+        let stalemate = BitBoardGame::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(
+            negamax(
+                &stalemate,
+                &TaperedEvaluator,
+                &mut SearchState::new(8),
+                3,
+                0,
+                -MATE,
+                MATE
+            ),
+            0
+        );
+        assert!(!is_mate_score(0));
+    }
+
+    #[test]
+    fn easy_tactics_suite() {
+        let suite = "
+            6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"back rank\";
+            4k3/8/8/3q4/8/8/3R4/4K3 w - - bm Rxd5; id \"free queen\";
+            4k3/8/2p5/3n4/8/8/3Q4/4K3 w - - am Qxd5; id \"defended knight\";
+            6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Kf2; id \"wrong answer\";
+        ";
+        let results = bestmove_suite(suite, 2);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].chosen.as_deref(), Some("Ra8#"));
+        assert_eq!(results[1].chosen.as_deref(), Some("Rxd5"));
+        let solved: Vec<bool> = results.iter().map(|result| result.solved).collect();
+        assert_eq!(solved, [true, true, true, false]);
+        assert_eq!(results[3].id.as_deref(), Some("wrong answer"));
+        assert_eq!(suite_score(&results), "3/4 solved");
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        let gs = BitBoardGame::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let (mov, score) = iterative_deepening(&gs, &ConstantEvaluator, 3).unwrap();
+        assert!(gs.is_legal(&mov));
+        assert_eq!(score, -7);
+
+        // mates are still found however positions are scored
+        let mate = BitBoardGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (mov, score) = iterative_deepening(&mate, &ConstantEvaluator, 4).unwrap();
+        assert_eq!(mov.to_uci(), "a1a8");
+        assert_eq!(score, MATE - 1);
+    }
+
+    fn uci_order(gs: &BitBoardGame, state: &SearchState, tt_move: u16, ply: usize) -> Vec<String> {
+        let mut moves = gs.moves();
+        state.order_moves(gs, &mut moves, tt_move, ply);
+        moves.iter().map(|mov| mov.to_uci()).collect()
+    }
+
+    #[test]
+    fn table_move_ordering() {
+        let gs = BitBoardGame::new();
+        let state = SearchState::new(8);
+        let mut moves = gs.moves();
+        let last = moves.last().unwrap().mov;
+        state.order_moves(&gs, &mut moves, last, 0);
+        assert_eq!(moves[0].mov, last);
+        assert_eq!(moves.len(), 20);
+    }
+
+    #[test]
+    fn killer_and_history_ordering() {
+        let mut state = SearchState::new(8);
+        let mut gs = BitBoardGame::new();
+        gs.play_uci(&["e2e4", "e7e5"]).unwrap();
+        let mut sibling = BitBoardGame::new();
+        sibling.play_uci(&["e2e4", "d7d5"]).unwrap();
+
+        // b1a3 refuted e7e5, it is tried first after d7d5 too except for the
+        // capture
+        state.record_cutoff(
+            gs.moves()
+                .iter()
+                .find(|m| m.to_uci() == "b1a3")
+                .unwrap()
+                .mov,
+            2,
+            3,
+        );
+        assert_eq!(uci_order(&gs, &state, 0, 2)[0], "b1a3");
+        assert_eq!(&uci_order(&sibling, &state, 0, 2)[..2], ["e4d5", "b1a3"]);
+        assert_eq!(state.killers(2)[1], 0);
+
+        // a second killer pushes the first to the other slot, the deeper
+        // history score of the first puts it ahead at other plies
+        let h3 = gs
+            .moves()
+            .iter()
+            .find(|m| m.to_uci() == "h2h3")
+            .unwrap()
+            .mov;
+        state.record_cutoff(h3, 2, 1);
+        assert_eq!(&uci_order(&gs, &state, 0, 2)[..2], ["h2h3", "b1a3"]);
+        assert_eq!(&uci_order(&gs, &state, 0, 4)[..2], ["b1a3", "h2h3"]);
+        // the table move still comes first
+        assert_eq!(uci_order(&gs, &state, h3, 4)[0], "h2h3");
+
+        // searching records cutoffs
+        let mut state = SearchState::new(12);
+        negamax(&gs, &TaperedEvaluator, &mut state, 3, 0, -MATE, MATE);
+        assert!((0..3).any(|ply| state.killers(ply)[0] != 0));
+    }
+
+    #[test]
+    fn search_principal_variation() {
+        // Qh6+ Kg8 Qg7#
+        let gs = BitBoardGame::from_fen("7k/8/5K2/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        let mut state = SearchState::new(16);
+        let (mov, score) = best_move_with_state(&gs, &TaperedEvaluator, &mut state, 4).unwrap();
+        assert_eq!(
+            state.table.get(gs.polyglot_hash()).unwrap().best_move,
+            mov.mov
+        );
+        assert_eq!(score, MATE - 3);
+        let pv = gs.extract_pv(&state.table, 10);
+        assert_eq!(pv.len(), 3);
+        assert_eq!(pv[0], mov.mov);
+
+        // the same search again starts from the stored move
+        let again = best_move_with_state(&gs, &TaperedEvaluator, &mut state, 4).unwrap();
+        assert_eq!((again.0.mov, again.1), (mov.mov, score));
+    }
+
+    #[test]
+    fn aspiration_windows() {
+        let kiwipete = BitBoardGame::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let (_, score) = iterative_deepening(&kiwipete, &TaperedEvaluator, 4).unwrap();
+        assert_eq!(score, best_move(&kiwipete, &TaperedEvaluator, 4).unwrap().1);
+
+        // taking the defended queen looks good at depth 1 and bad at depth 2
+        let trap = BitBoardGame::from_fen("4k3/8/2p5/3q4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let mut state = SearchState::new(12);
+        let (_, score) =
+            iterative_deepening_with_state(&trap, &TaperedEvaluator, &mut state, 3).unwrap();
+        assert!(state.fail_lows > 0);
+        assert_eq!(score, best_move(&trap, &TaperedEvaluator, 3).unwrap().1);
+    }
+
+    #[test]
+    fn stop_flag() {
+        let gs = BitBoardGame::new();
+        let mut state = SearchState::new(16);
+        let stop = state.stop.clone();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        let (mov, _) =
+            iterative_deepening_with_state(&gs, &TaperedEvaluator, &mut state, 64).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        stopper.join().unwrap();
+        assert!(state.stopped());
+        assert!(gs.is_legal(&mov));
+
+        // stopped before it starts still gives a move
+        let mut state = SearchState::new(8);
+        state.stop.store(true, Ordering::Relaxed);
+        state.nodes = 1023;
+        assert!(iterative_deepening_with_state(&gs, &TaperedEvaluator, &mut state, 64).is_some());
+        assert!(state.stopped());
+    }
+
+    #[test]
+    fn repetition_draw() {
+        // a queen down, only the perpetual Qh5+ Kg8 Qe8+ Kh7 saves white
+        let gs = BitBoardGame::from_fen("8/6pk/8/8/8/rq6/r3Q1PP/7K w - - 0 1").unwrap();
+        assert!(TaperedEvaluator.eval(&gs) < -500);
+        let (mov, score) = best_move(&gs, &TaperedEvaluator, 6).unwrap();
+        assert_eq!(mov.to_uci(), "e2h5");
+        assert_eq!(score, 0);
+
+        let mut state = SearchState::new(8);
+        assert!(!state.is_repetition(gs.polyglot_hash()));
+        state.path = vec![gs.polyglot_hash(), 1];
+        assert!(state.is_repetition(gs.polyglot_hash()));
+        state.path.push(2);
+        assert!(!state.is_repetition(gs.polyglot_hash()));
+    }
+
+    #[test]
+    fn contempt() {
+        // a queen down on the last ply before the fifty move rule, any quiet
+        // move draws and only a pawn move or capture plays on
+        let gs = BitBoardGame::from_fen("8/6pk/8/8/8/rq6/r3Q1PP/7K w - - 99 80").unwrap();
+        let mut state = SearchState::new(16);
+        let (mov, score) = best_move_with_state(&gs, &TaperedEvaluator, &mut state, 4).unwrap();
+        let mut child = gs.clone();
+        child.do_move(&mov);
+        assert_eq!(child.halfmove_clock(), 100);
+        assert_eq!(score, 0);
+
+        let mut state = SearchState::new(16);
+        state.contempt = 2000;
+        assert_eq!(state.draw_score(0), -2000);
+        assert_eq!(state.draw_score(1), 2000);
+        let (mov, score) = best_move_with_state(&gs, &TaperedEvaluator, &mut state, 4).unwrap();
+        let mut child = gs.clone();
+        child.do_move(&mov);
+        assert_eq!(child.halfmove_clock(), 0);
+        assert!(score < 0 && score > -2000);
+    }
+}
@@ -0,0 +1,116 @@
+/// how a stored score relates to the true score of the position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+    Exact,
+    /// the search failed high, the true score is at least this
+    LowerBound,
+    /// the search failed low, the true score is at most this
+    UpperBound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TTVal {
+    pub flag: Flag,
+    pub depth: u8,
+    pub value: i32,
+    pub full_hash: u64,
+    /// packed best or refutation move found for the position, 0 if none
+    pub best_move: u16,
+}
+
+/// Transposition table of search results indexed by the low bits of the
+/// position hash, a new entry always replaces the old one in its slot
+pub struct TTable {
+    table: Vec<Option<TTVal>>,
+    mask: u64,
+}
+
+impl TTable {
+    /// a table of `1 << table_bits` entries
+    pub fn new(table_bits: u8) -> Self {
+        Self {
+            table: vec![None; 1 << table_bits],
+            mask: (1 << table_bits) - 1,
+        }
+    }
+
+    pub fn insert(&mut self, entry: TTVal) {
+        self.table[(entry.full_hash & self.mask) as usize] = Some(entry);
+    }
+
+    /// the entry stored for exactly `hash`
+    pub fn get(&self, hash: u64) -> Option<&TTVal> {
+        self.table[(hash & self.mask) as usize]
+            .as_ref()
+            .filter(|entry| entry.full_hash == hash)
+    }
+
+    pub fn clear(&mut self) {
+        self.table.fill(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bit_board::BitBoardGame,
+        game::{ChessGame, Move},
+        tt::{Flag, TTVal, TTable},
+    };
+
+    fn entry(hash: u64, best_move: u16) -> TTVal {
+        TTVal {
+            flag: Flag::Exact,
+            depth: 1,
+            value: 0,
+            full_hash: hash,
+            best_move,
+        }
+    }
+
+    #[test]
+    fn table_slots() {
+        let mut table = TTable::new(4);
+        table.insert(entry(0x31, 1));
+        assert_eq!(table.get(0x31).map(|e| e.best_move), Some(1));
+        // same slot, different position
+        assert!(table.get(0x21).is_none());
+        table.insert(entry(0x21, 2));
+        assert!(table.get(0x31).is_none());
+        assert_eq!(table.get(0x21).map(|e| e.best_move), Some(2));
+        table.clear();
+        assert!(table.get(0x21).is_none());
+    }
+
+    /// store `line` from the start position in `table` as the best moves
+    fn store_line(table: &mut TTable, line: &[&str]) {
+        let mut gs = BitBoardGame::new();
+        for uci in line {
+            let mov = gs.moves().into_iter().find(|m| m.to_uci() == *uci).unwrap();
+            table.insert(entry(gs.polyglot_hash(), mov.mov));
+            gs.do_move(&mov);
+        }
+    }
+
+    #[test]
+    fn principal_variation() {
+        let start = BitBoardGame::new();
+        let mut table = TTable::new(12);
+        assert!(start.extract_pv(&table, 10).is_empty());
+
+        let line = ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"];
+        store_line(&mut table, &line);
+        let pv: Vec<String> = start
+            .extract_pv(&table, 10)
+            .iter()
+            .map(|mov| mov.to_uci())
+            .collect();
+        assert_eq!(pv, line);
+        assert_eq!(start.extract_pv(&table, 3).len(), 3);
+
+        // a line back to the start stops at the repetition
+        let mut table = TTable::new(12);
+        store_line(&mut table, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(start.extract_pv(&table, 10).len(), 4);
+    }
+}
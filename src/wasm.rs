@@ -0,0 +1,88 @@
+//! JavaScript bindings for the bitboard engine, built with the `wasm`
+//! feature. The library is an rlib so build the module with
+//! `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`
+//! then run `wasm-bindgen` on the output.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    bit_board::BitBoardGame,
+    eval::TaperedEvaluator,
+    game::{ChessGame, Move},
+    search,
+};
+
+/// A game for JavaScript, moves go in and out as UCI strings
+#[wasm_bindgen]
+pub struct WasmGame {
+    game: BitBoardGame,
+}
+
+#[wasm_bindgen]
+impl WasmGame {
+    /// the standard starting position
+    #[wasm_bindgen(constructor)]
+    pub fn new_game() -> WasmGame {
+        WasmGame {
+            game: BitBoardGame::new(),
+        }
+    }
+
+    /// the position written as `fen`, undefined if it doesn't parse
+    pub fn from_fen(fen: &str) -> Option<WasmGame> {
+        BitBoardGame::from_fen(fen).map(|game| WasmGame { game })
+    }
+
+    /// every legal move as a UCI string
+    pub fn legal_moves_uci(&self) -> Vec<String> {
+        self.game.legal_uci()
+    }
+
+    /// Play `uci`, false leaving the position as it was if it isn't legal
+    pub fn push_uci(&mut self, uci: &str) -> bool {
+        match self.game.move_from_uci(uci) {
+            Some(mov) => {
+                self.game.do_move(&mov);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn to_fen(&self) -> String {
+        self.game.to_fen()
+    }
+
+    /// the best move searching `depth` plies as UCI, undefined if the game
+    /// is over
+    pub fn best_move(&self, depth: u8) -> Option<String> {
+        search::best_move(&self.game, &TaperedEvaluator, depth).map(|(mov, _)| mov.to_uci())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wasm::WasmGame;
+
+    #[test]
+    fn wrapper_round_trip() {
+        let mut game = WasmGame::new_game();
+        assert_eq!(game.legal_moves_uci().len(), 20);
+        assert!(game.push_uci("e2e4"));
+        assert!(!game.push_uci("e2e4"));
+        assert!(game.push_uci("e7e5"));
+        let fen = game.to_fen();
+        assert_eq!(
+            fen,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        assert_eq!(WasmGame::from_fen(&fen).unwrap().to_fen(), fen);
+        assert!(WasmGame::from_fen("not a fen").is_none());
+
+        let mate = WasmGame::from_fen("6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1").unwrap();
+        assert_eq!(mate.best_move(2).as_deref(), Some("d1d8"));
+        assert!(mate.legal_moves_uci().contains(&"d1d8".to_string()));
+        let mated = WasmGame::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(mated.best_move(2), None);
+    }
+}
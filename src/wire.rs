@@ -0,0 +1,71 @@
+//! Moves packed into 16 bits for sending over the network or logging, laid
+//! out as described on `BBMove`: the from square in bits 0-5, the to square
+//! in bits 6-11, the promotion piece (knight, bishop, rook, queen) in bits
+//! 12-13 and a promotion flag in bit 14. Squares are numbered as by
+//! `bit_board::square_index` and castles are written as the king moving to
+//! its square, or onto its rook in Chess960.
+
+use crate::{
+    bit_board::{BitBoardGame, BitBoardGameMove},
+    game::ChessGame,
+};
+
+/// `mov` packed into 16 bits
+pub fn encode_move(mov: &BitBoardGameMove) -> u16 {
+    mov.mov
+}
+
+/// The legal move in `game` packed as `packed`, None if there isn't one
+pub fn decode_move(packed: u16, game: &BitBoardGame) -> Option<BitBoardGameMove> {
+    game.moves().into_iter().find(|mov| mov.mov == packed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bit_board::{square_index, BitBoardGame},
+        game::{ChessGame, Move},
+        wire::{decode_move, encode_move},
+    };
+
+    fn round_trip(game: &BitBoardGame, uci: &str) -> u16 {
+        let mov = game.move_from_uci(uci).unwrap();
+        let packed = encode_move(&mov);
+        let decoded = decode_move(packed, game).unwrap();
+        assert_eq!(decoded.to_uci(), uci);
+        let (mut expected, mut found) = (game.clone(), game.clone());
+        expected.do_move(&mov);
+        found.do_move(&decoded);
+        assert_eq!(found.to_fen(), expected.to_fen());
+        packed
+    }
+
+    #[test]
+    fn wire_round_trip() {
+        let start = BitBoardGame::new();
+        let packed = round_trip(&start, "g1f3");
+        let (g1, f3) = (square_index(6, 0), square_index(5, 2));
+        assert_eq!(packed, g1 as u16 | (f3 as u16) << 6);
+
+        let promotion = BitBoardGame::from_fen("3r4/4P1k1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let queen = round_trip(&promotion, "e7e8q");
+        assert_eq!(queen >> 12, 0b111);
+        let knight = round_trip(&promotion, "e7d8n");
+        assert_eq!(knight >> 12, 0b100);
+
+        let en_passant = BitBoardGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        round_trip(&en_passant, "e5d6");
+
+        let castles = BitBoardGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        round_trip(&castles, "e1g1");
+        round_trip(&castles, "e1c1");
+        let chess960 =
+            BitBoardGame::from_fen("1r2k1r1/6p1/8/8/8/8/6P1/1R2K1R1 w GBgb - 0 1").unwrap();
+        round_trip(&chess960, "e1g1");
+        round_trip(&chess960, "e1b1");
+
+        // moves that aren't legal in the position
+        assert!(decode_move(packed, &promotion).is_none());
+        assert!(decode_move(0, &start).is_none());
+    }
+}